@@ -0,0 +1,14 @@
+// Rings rippling out from the middle, or from wherever the mouse was
+// clicked last. The inputs are in `shadertoy`.
+
+fn mainImage(fragCoord: vec2<f32>) -> vec4<f32> {
+    var center = 0.5 * shadertoy.iResolution.xy;
+    if (shadertoy.iMouse.x > 0.0 || shadertoy.iMouse.y > 0.0) {
+        center = shadertoy.iMouse.xy;
+    }
+    let d = length(fragCoord - center) / shadertoy.iResolution.y;
+    let wave = 0.5 + 0.5 * sin(d * 40.0 - shadertoy.iTime * 4.0);
+    let fade = exp(-d * 3.0);
+    let color = mix(vec3<f32>(0.1, 0.0, 0.3), vec3<f32>(0.1, 0.9, 1.0), wave * fade);
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,44 @@
+// A banded sun sinking behind a scrolling grid, dragging with the mouse
+// moves the horizon.
+
+vec3 sky(vec2 p)
+{
+    vec3 top = vec3(0.02, 0.0, 0.04);
+    vec3 horizon = vec3(0.55, 0.1, 0.6);
+    return mix(horizon, top, clamp(p.y * 1.5, 0.0, 1.0));
+}
+
+vec3 sun(vec2 p, vec3 background)
+{
+    float radius = 0.35;
+    vec2 center = vec2(0.0, 0.18);
+    float d = length(p - center);
+    // Gaps cut into the lower half, widening towards the bottom
+    float band = fract((p.y - center.y) * 18.0 - iTime * 0.5);
+    float gap = clamp((center.y - p.y) * 4.0, 0.0, 0.7);
+    if (d > radius || (p.y < center.y && band < gap)) {
+        return background;
+    }
+    return mix(vec3(1.0, 0.2, 0.55), vec3(1.0, 0.85, 0.1), (p.y - center.y) / radius * 0.5 + 0.5);
+}
+
+vec3 grid(vec2 p)
+{
+    // Project the floor, further away towards the horizon
+    float depth = 0.4 / max(-p.y, 0.001);
+    vec2 floor = vec2(p.x * depth, depth + iTime * 2.0);
+    vec2 lines = abs(fract(floor) - 0.5) / fwidth(floor);
+    float glow = 1.0 - clamp(min(lines.x, lines.y), 0.0, 1.0);
+    float fade = clamp(-p.y * 4.0, 0.0, 1.0);
+    return vec3(1.0, 0.1, 1.0) * glow * fade;
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    vec2 p = (fragCoord - 0.5 * iResolution.xy) / iResolution.y;
+    if (iMouse.z > 0.0) {
+        p.y -= iMouse.y / iResolution.y - 0.5;
+    }
+    vec3 color = p.y > 0.0 ? sun(p, sky(p)) : grid(p);
+    fragColor = vec4(color, 1.0);
+}
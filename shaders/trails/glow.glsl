@@ -0,0 +1,14 @@
+// Buffer A, with a soft glow from the pixels around each one
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec2 pixel = 1.0 / iResolution.xy;
+    vec3 glow = vec3(0.0);
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            glow += texture(iChannel0, uv + vec2(x, y) * pixel * 3.0).rgb;
+        }
+    }
+    vec3 color = texture(iChannel0, uv).rgb + glow / 25.0 * 0.8;
+    fragColor = vec4(color, 1.0);
+}
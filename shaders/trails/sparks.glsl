@@ -0,0 +1,25 @@
+// Sparks on Lissajous curves, over what buffer A had last frame faded a bit
+
+const int SPARKS = 6;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec3 last = texture(iChannel0, uv).rgb;
+
+    vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    vec3 sparks = vec3(0.0);
+    for (int i = 0; i < SPARKS; i++) {
+        float f = float(i);
+        float t = iTime * (0.6 + 0.1 * f);
+        vec2 center = vec2(sin(t * 1.3 + f), cos(t * 0.9 + f * 2.0)) * vec2(0.8, 0.7);
+        vec3 color = 0.5 + 0.5 * cos(6.2831 * (f / float(SPARKS) + vec3(0.0, 0.33, 0.67)));
+        sparks += color * smoothstep(0.04, 0.0, length(p - center));
+    }
+    // The pointer draws too while a button is down
+    if (iMouse.z > 0.0) {
+        vec2 mouse = (2.0 * iMouse.xy - iResolution.xy) / iResolution.y;
+        sparks += smoothstep(0.05, 0.0, length(p - mouse));
+    }
+
+    fragColor = vec4(max(last * 0.96, sparks), 1.0);
+}
@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use x11::xlib::Window;
+
+use crate::hacks;
+use crate::params::{Kind, Parameter, Value};
+use crate::post;
+
+pub const USAGE: &str = "\
+usage: xscreensaver_wgpu [options]
+
+  -root                 draw on the root window (or XSCREENSAVER_WINDOW)
+  -window               draw in a window of our own
+  -window-id ID         draw on an existing window, decimal or 0x hex
+  -geometry WxH[+X+Y]   size and position of our own window
+  -install              accepted for compatibility, ignored
+  -visual VISUAL        accepted for compatibility, ignored
+  -offscreen            render without a window, size from -geometry
+  -render-frames N      render N frames offscreen to PNGs and exit
+  -output DIR           where -render-frames puts its PNGs, default .
+  -software             use the software/fallback adapter
+  -reload-shaders       rebuild shaders edited in the source tree, with -window
+  -print-xml            print the xscreensaver-settings XML and exit
+  -help                 show this message
+
+hack options, see -print-xml for their ranges:
+";
+
+/// Options shown in xscreensaver-settings that aren't specific to a hack.
+pub const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        id: "hack",
+        label: "Screensaver",
+        arg: "-hack",
+        kind: Kind::Select {
+            options: hacks::HACKS,
+            default: "ground",
+        },
+    },
+    Parameter {
+        id: "delay",
+        label: "Frame rate",
+        arg: "-delay",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 100_000.0,
+            // 30 FPS
+            default: 33_333.0,
+            low_label: "Low",
+            high_label: "High",
+            invert: true,
+        },
+    },
+    Parameter {
+        id: "samples",
+        label: "Antialiasing",
+        arg: "-samples",
+        kind: Kind::Select {
+            // All wgpu takes for now
+            options: &[("1", "Off"), ("4", "4x MSAA")],
+            default: "4",
+        },
+    },
+    Parameter {
+        id: "camerapath",
+        label: "Camera path file",
+        arg: "-camera-path",
+        kind: Kind::File,
+    },
+    Parameter {
+        id: "skytop",
+        label: "Sky color",
+        arg: "-sky-top",
+        kind: Kind::Color { default: "#05000a" },
+    },
+    Parameter {
+        id: "skyhorizon",
+        label: "Horizon color",
+        arg: "-sky-horizon",
+        kind: Kind::Color { default: "#4a0e5c" },
+    },
+    Parameter {
+        id: "suncolor",
+        label: "Sun color",
+        arg: "-sun-color",
+        kind: Kind::Color { default: "#ffd319" },
+    },
+    Parameter {
+        id: "sunsize",
+        label: "Sun size",
+        arg: "-sun-size",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 0.6,
+            default: 0.2,
+            low_label: "None",
+            high_label: "Huge",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "stars",
+        label: "Stars",
+        arg: "-stars",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "showfps",
+        label: "Show frame rate",
+        arg: "-fps",
+        kind: Kind::Toggle,
+    },
+];
+
+/// Every option the binary understands beyond the xscreensaver basics.
+pub fn parameters() -> impl Iterator<Item = &'static Parameter> {
+    PARAMETERS
+        .iter()
+        .chain(post::PARAMETERS)
+        .chain(hacks::parameters())
+}
+
+/// The usage message, listing the hack options too.
+pub fn usage() -> String {
+    let mut usage = USAGE.to_string();
+    for parameter in parameters() {
+        let arg = if parameter.is_flag() {
+            parameter.arg.to_string()
+        } else {
+            format!("{} VALUE", parameter.arg)
+        };
+        usage.push_str(&format!(
+            "  {:<21} {}\n",
+            arg,
+            parameter.label.to_lowercase()
+        ));
+    }
+    usage
+}
+
+/// Where to draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowTarget {
+    /// The window xscreensaver hands us in `XSCREENSAVER_WINDOW`, or a
+    /// window of our own for development.
+    Auto,
+    Window,
+    Root,
+    Id(Window),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 800,
+            x: 0,
+            y: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub target: WindowTarget,
+    pub geometry: Geometry,
+    pub offscreen: bool,
+    pub render_frames: Option<u32>,
+    pub output: PathBuf,
+    pub software: bool,
+    pub reload_shaders: bool,
+    pub help: bool,
+    pub print_xml: bool,
+    /// Values given for [`parameters`], by id.
+    values: HashMap<&'static str, Value>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            target: WindowTarget::Auto,
+            geometry: Geometry::default(),
+            offscreen: false,
+            render_frames: None,
+            output: PathBuf::from("."),
+            software: false,
+            reload_shaders: false,
+            help: false,
+            print_xml: false,
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl Args {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Parse xscreensaver style arguments, `-name value`. The GNU style
+    /// `--name` is accepted as well.
+    pub fn parse<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut result = Args::default();
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            let name = if arg.starts_with("--") {
+                &arg[1..]
+            } else {
+                &arg
+            };
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match name {
+                "-root" => result.target = WindowTarget::Root,
+                "-window" => result.target = WindowTarget::Window,
+                "-window-id" => result.target = WindowTarget::Id(parse_window_id(&value()?)?),
+                "-geometry" => result.geometry = parse_geometry(&value()?)?,
+                "-install" => {}
+                "-visual" => {
+                    value()?;
+                }
+                "-offscreen" => result.offscreen = true,
+                "-render-frames" => {
+                    let value = value()?;
+                    let frames = value
+                        .parse()
+                        .map_err(|_| format!("invalid frame count: {}", value))?;
+                    result.render_frames = Some(frames);
+                    result.offscreen = true;
+                }
+                "-output" => result.output = PathBuf::from(value()?),
+                "-software" => result.software = true,
+                "-reload-shaders" => result.reload_shaders = true,
+                "-print-xml" => result.print_xml = true,
+                "-help" | "-h" => result.help = true,
+                "-samples" => {
+                    let value = value()?;
+                    let parameter = parameters()
+                        .find(|parameter| parameter.id == "samples")
+                        .expect("-samples is a parameter");
+                    result.values.insert(
+                        parameter.id,
+                        parameter.parse(Some(supported_samples(&value)))?,
+                    );
+                }
+                _ => {
+                    let parameter = parameters()
+                        .find(|parameter| parameter.arg == name)
+                        .ok_or_else(|| format!("unknown option: {}", arg))?;
+                    let value = if parameter.is_flag() {
+                        None
+                    } else {
+                        Some(value()?)
+                    };
+                    result
+                        .values
+                        .insert(parameter.id, parameter.parse(value.as_deref())?);
+                }
+            }
+        }
+        // Each frame is a step of the delay, they'd all be the same
+        if result.render_frames.is_some() && result.delay().is_zero() {
+            return Err("-render-frames needs a -delay above 0".to_string());
+        }
+        Ok(result)
+    }
+
+    /// The value of the parameter `id`, or its default.
+    ///
+    /// Panics if there's no such parameter, that's a typo on our side.
+    pub fn value(&self, id: &str) -> Value {
+        match self.values.get(id) {
+            Some(value) => value.clone(),
+            None => parameters()
+                .find(|parameter| parameter.id == id)
+                .unwrap_or_else(|| panic!("no parameter {}", id))
+                .default_value(),
+        }
+    }
+
+    pub fn number(&self, id: &str) -> f64 {
+        match self.value(id) {
+            Value::Number(number) => number,
+            value => panic!("parameter {} isn't a number: {:?}", id, value),
+        }
+    }
+
+    pub fn flag(&self, id: &str) -> bool {
+        match self.value(id) {
+            Value::Flag(flag) => flag,
+            value => panic!("parameter {} isn't a flag: {:?}", id, value),
+        }
+    }
+
+    pub fn text(&self, id: &str) -> String {
+        match self.value(id) {
+            Value::Text(text) => text,
+            value => panic!("parameter {} isn't text: {:?}", id, value),
+        }
+    }
+
+    /// Time to wait between frames.
+    pub fn delay(&self) -> Duration {
+        Duration::from_micros(self.number("delay") as u64)
+    }
+
+    /// MSAA samples per pixel, 1 being no MSAA.
+    pub fn sample_count(&self) -> u32 {
+        self.text("samples")
+            .parse()
+            .expect("validated when parsing")
+    }
+
+    pub fn show_fps(&self) -> bool {
+        self.flag("showfps")
+    }
+}
+
+/// The nearest of the `-samples` counts wgpu renders with to `value`, so
+/// settings written for other counts MSAA knows still work.
+fn supported_samples(value: &str) -> &str {
+    match value {
+        "2" | "8" => {
+            log::warn!("-samples {} isn't supported, using 4", value);
+            "4"
+        }
+        _ => value,
+    }
+}
+
+/// Window ids come as `0x...` from xscreensaver, but decimal works too.
+pub fn parse_window_id(value: &str) -> Result<Window, String> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => Window::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|_| format!("invalid window id: {}", value))
+}
+
+/// `WIDTHxHEIGHT` with an optional `+X+Y`.
+fn parse_geometry(value: &str) -> Result<Geometry, String> {
+    let invalid = || format!("invalid geometry: {}", value);
+    let (size, position) = match value.find(['+', '-']) {
+        Some(i) => value.split_at(i),
+        None => (value, ""),
+    };
+    let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let mut geometry = Geometry {
+        width: width.parse().map_err(|_| invalid())?,
+        height: height.parse().map_err(|_| invalid())?,
+        ..Geometry::default()
+    };
+    // Nothing can be drawn into that
+    if geometry.width == 0 || geometry.height == 0 {
+        return Err(invalid());
+    }
+    if !position.is_empty() {
+        let split = position[1..].find(['+', '-']).ok_or_else(invalid)? + 1;
+        let (x, y) = position.split_at(split);
+        geometry.x = x.parse().map_err(|_| invalid())?;
+        geometry.y = y.parse().map_err(|_| invalid())?;
+    }
+    Ok(geometry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().copied())
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let args = parse(&[]).expect("valid");
+        assert_eq!(args.target, WindowTarget::Auto);
+        assert_eq!(args.geometry, Geometry::default());
+        assert_eq!(args.delay(), Duration::from_micros(33_333));
+        assert!(!args.show_fps());
+    }
+
+    #[test]
+    fn xscreensaver_arguments() {
+        let args = parse(&["-root", "-delay", "10000", "-fps", "-install"]).expect("valid");
+        assert_eq!(args.target, WindowTarget::Root);
+        assert_eq!(args.delay(), Duration::from_micros(10_000));
+        assert!(args.show_fps());
+
+        let args = parse(&["-window-id", "0x1a00003", "-visual", "default"]).expect("valid");
+        assert_eq!(args.target, WindowTarget::Id(0x1a00003));
+    }
+
+    #[test]
+    fn gnu_style_arguments() {
+        let args = parse(&["--window", "--geometry", "640x480"]).expect("valid");
+        assert_eq!(args.target, WindowTarget::Window);
+        assert_eq!((args.geometry.width, args.geometry.height), (640, 480));
+        assert!(parse(&["--help"]).expect("valid").help);
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert_eq!(
+            parse(&["-frobnicate"]).unwrap_err(),
+            "unknown option: -frobnicate"
+        );
+        assert_eq!(
+            parse(&["-window-id"]).unwrap_err(),
+            "-window-id needs a value"
+        );
+        assert_eq!(
+            parse(&["-delay", "soon"]).unwrap_err(),
+            "invalid value for -delay: soon"
+        );
+        assert_eq!(
+            parse(&["-delay", "0", "-render-frames", "10"]).unwrap_err(),
+            "-render-frames needs a -delay above 0"
+        );
+        assert!(parse(&["-delay", "0"]).is_ok());
+        assert_eq!(
+            parse(&["-samples", "3"]).unwrap_err(),
+            "invalid value for -samples: 3"
+        );
+    }
+
+    #[test]
+    fn unsupported_sample_counts_fall_back_to_4() {
+        for samples in ["2", "4", "8"] {
+            assert_eq!(parse(&["-samples", samples]).unwrap().sample_count(), 4);
+        }
+        assert_eq!(parse(&["-samples", "1"]).unwrap().sample_count(), 1);
+    }
+
+    #[test]
+    fn geometry_with_and_without_position() {
+        assert_eq!(
+            parse_geometry("800x600"),
+            Ok(Geometry {
+                width: 800,
+                height: 600,
+                x: 0,
+                y: 0
+            })
+        );
+        assert_eq!(
+            parse_geometry("800X600+10-20"),
+            Ok(Geometry {
+                width: 800,
+                height: 600,
+                x: 10,
+                y: -20
+            })
+        );
+    }
+
+    #[test]
+    fn bad_geometry_is_rejected() {
+        for geometry in ["", "800", "800x", "x600", "800x600+10", "800x600+a+b"] {
+            assert!(parse_geometry(geometry).is_err(), "{}", geometry);
+        }
+        assert_eq!(
+            parse_geometry("0x600"),
+            Err("invalid geometry: 0x600".to_string())
+        );
+        assert!(parse_geometry("800x0").is_err());
+    }
+
+    #[test]
+    fn window_ids_in_decimal_or_hex() {
+        assert_eq!(parse_window_id("12345"), Ok(12345));
+        assert_eq!(parse_window_id("0x1a00003"), Ok(0x1a00003));
+        assert_eq!(parse_window_id("0X1A00003"), Ok(0x1a00003));
+        assert_eq!(parse_window_id(" 42\n"), Ok(42));
+    }
+
+    #[test]
+    fn bad_window_ids_are_rejected() {
+        for id in ["", "0x", "window", "0xzz", "-1"] {
+            assert_eq!(
+                parse_window_id(id),
+                Err(format!("invalid window id: {}", id.trim())),
+                "{}",
+                id
+            );
+        }
+    }
+}
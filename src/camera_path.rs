@@ -0,0 +1,410 @@
+use cgmath::{EuclideanSpace, Point3, Vector3};
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+use crate::primitives::Camera;
+
+/// How values between two keyframes are worked out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    /// Smooth curve through all the keys.
+    CatmullRom,
+    /// Straight between keys, but slowing down into and out of each one.
+    Ease,
+}
+
+impl FromStr for Interpolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Interpolation::Linear),
+            "catmull-rom" => Ok(Interpolation::CatmullRom),
+            "ease" => Ok(Interpolation::Ease),
+            _ => Err(format!("unknown interpolation: {}", s)),
+        }
+    }
+}
+
+/// What happens after the last keyframe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playback {
+    /// Stay at the last keyframe.
+    Once,
+    /// Start over from the first keyframe.
+    Loop,
+    /// Play backwards to the first keyframe, then forwards again.
+    PingPong,
+}
+
+impl FromStr for Playback {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "once" => Ok(Playback::Once),
+            "loop" => Ok(Playback::Loop),
+            "pingpong" => Ok(Playback::PingPong),
+            _ => Err(format!("unknown playback: {}", s)),
+        }
+    }
+}
+
+/// Values that can be keyframed.
+pub trait Animatable:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self>
+{
+}
+
+impl<T> Animatable for T where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T> {}
+
+fn lerp<T: Animatable>(a: T, b: T, t: f32) -> T {
+    a + (b - a) * t
+}
+
+/// Uniform Catmull-Rom spline through `p1` and `p2`.
+fn catmull_rom<T: Animatable>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// Keyframes of a single value, sorted by time.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    keys: Vec<(f64, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    pub fn insert(&mut self, time: f64, value: T) {
+        let index = self.keys.partition_point(|(t, _)| *t <= time);
+        self.keys.insert(index, (time, value));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f64 {
+        self.keys.last().map_or(0.0, |(time, _)| *time)
+    }
+
+    /// The value at `time`, held at the first and last keyframes outside of
+    /// them.
+    pub fn sample(&self, time: f64) -> Option<T> {
+        let (first, last) = (self.keys.first()?, self.keys.last()?);
+        if time <= first.0 {
+            return Some(first.1);
+        }
+        if time >= last.0 {
+            return Some(last.1);
+        }
+        // The first key after `time`, there's always one before it too
+        let next = self.keys.partition_point(|(t, _)| *t <= time);
+        let (t1, p1) = self.keys[next - 1];
+        let (t2, p2) = self.keys[next];
+        let t = ((time - t1) / (t2 - t1)) as f32;
+        Some(match self.interpolation {
+            Interpolation::Linear => lerp(p1, p2, t),
+            Interpolation::Ease => lerp(p1, p2, t * t * (3.0 - 2.0 * t)),
+            Interpolation::CatmullRom => {
+                // Repeat the end points for the missing neighbours
+                let p0 = self.keys[next.saturating_sub(2)].1;
+                let p3 = self.keys[(next + 1).min(self.keys.len() - 1)].1;
+                catmull_rom(p0, p1, p2, p3, t)
+            }
+        })
+    }
+}
+
+/// A flight path for the camera.
+///
+/// Described in a small text format, one directive per line and `#` starting
+/// a comment:
+///
+/// ```text
+/// playback pingpong          # once, loop or pingpong
+/// interpolation catmull-rom  # linear, catmull-rom or ease
+/// eye 0 0 1 2                # eye at 0 seconds is at (0, 1, 2)
+/// eye 20 4 3 -10
+/// target 0 0 0 -100
+/// fovy 0 45                  # in degrees
+/// fovy 20 60
+/// ```
+///
+/// Tracks without keys leave that part of the camera alone.
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    eye: Track<Vector3<f32>>,
+    target: Track<Vector3<f32>>,
+    fovy: Track<f32>,
+    playback: Playback,
+}
+
+impl CameraPath {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut path = CameraPath {
+            eye: Track::new(Interpolation::Linear),
+            target: Track::new(Interpolation::Linear),
+            fovy: Track::new(Interpolation::Linear),
+            playback: Playback::Loop,
+        };
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let directive = match words.next() {
+                Some(directive) => directive,
+                None => continue,
+            };
+            let error = |message: String| format!("line {}: {}", number + 1, message);
+            let words: Vec<&str> = words.collect();
+            let numbers = || {
+                words
+                    .iter()
+                    .map(|word| {
+                        word.parse::<f64>()
+                            .map_err(|_| error(format!("not a number: {}", word)))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            match (directive, words.len()) {
+                ("playback", 1) => path.playback = words[0].parse().map_err(error)?,
+                ("interpolation", 1) => {
+                    let interpolation = words[0].parse().map_err(error)?;
+                    path.eye.interpolation = interpolation;
+                    path.target.interpolation = interpolation;
+                    path.fovy.interpolation = interpolation;
+                }
+                ("eye" | "target", 4) => {
+                    let n = numbers()?;
+                    let value = Vector3::new(n[1] as f32, n[2] as f32, n[3] as f32);
+                    let track = if directive == "eye" {
+                        &mut path.eye
+                    } else {
+                        &mut path.target
+                    };
+                    track.insert(n[0], value);
+                }
+                ("fovy", 2) => {
+                    let n = numbers()?;
+                    path.fovy.insert(n[0], n[1] as f32);
+                }
+                ("playback" | "interpolation" | "eye" | "target" | "fovy", _) => {
+                    return Err(error(format!("wrong number of values for {}", directive)))
+                }
+                _ => return Err(error(format!("unknown directive: {}", directive))),
+            }
+        }
+        if path.eye.is_empty() && path.target.is_empty() && path.fovy.is_empty() {
+            return Err("no keyframes".to_string());
+        }
+        Ok(path)
+    }
+
+    /// Time of the last keyframe of any track.
+    pub fn duration(&self) -> f64 {
+        self.eye
+            .duration()
+            .max(self.target.duration())
+            .max(self.fovy.duration())
+    }
+
+    /// Where in the path we are `elapsed` seconds after starting.
+    fn path_time(&self, elapsed: f64) -> f64 {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return 0.0;
+        }
+        match self.playback {
+            Playback::Once => elapsed.min(duration),
+            Playback::Loop => elapsed.rem_euclid(duration),
+            Playback::PingPong => {
+                let t = elapsed.rem_euclid(duration * 2.0);
+                if t > duration {
+                    duration * 2.0 - t
+                } else {
+                    t
+                }
+            }
+        }
+    }
+
+    /// Move `camera` to where it should be `elapsed` seconds in.
+    pub fn apply(&self, camera: &mut Camera, elapsed: f64) {
+        let time = self.path_time(elapsed);
+        if let Some(eye) = self.eye.sample(time) {
+            camera.eye = Point3::from_vec(eye);
+        }
+        if let Some(target) = self.target.sample(time) {
+            camera.target = Point3::from_vec(target);
+        }
+        if let Some(fovy) = self.fovy.sample(time) {
+            camera.fovy = fovy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(interpolation: Interpolation, keys: &[(f64, f32)]) -> Track<f32> {
+        let mut track = Track::new(interpolation);
+        for &(time, value) in keys {
+            track.insert(time, value);
+        }
+        track
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn parses_the_format() {
+        let path = CameraPath::parse(
+            "# comment\n\
+             playback pingpong\n\
+             interpolation ease  # trailing comment\n\
+             eye 10 1 2 3\n\
+             eye 0 0 0 0\n\
+             fovy 20 60\n",
+        )
+        .expect("valid");
+        assert_eq!(path.playback, Playback::PingPong);
+        assert_eq!(path.eye.interpolation, Interpolation::Ease);
+        assert_eq!(path.fovy.interpolation, Interpolation::Ease);
+        // Sorted by time, whatever order they came in
+        assert_eq!(path.eye.sample(10.0), Some(Vector3::new(1.0, 2.0, 3.0)));
+        assert!(path.target.is_empty());
+        assert_eq!(path.duration(), 20.0);
+    }
+
+    #[test]
+    fn errors_say_which_line() {
+        let error = |text| CameraPath::parse(text).unwrap_err();
+        assert_eq!(
+            error("eye 0 0 0 0\n\nzoom 2"),
+            "line 3: unknown directive: zoom"
+        );
+        assert_eq!(error("eye 0 0 one 0"), "line 1: not a number: one");
+        assert_eq!(
+            error("# eye\nfovy 1 2 3"),
+            "line 2: wrong number of values for fovy"
+        );
+        assert_eq!(error("playback twice"), "line 1: unknown playback: twice");
+        assert_eq!(
+            error("interpolation cubic"),
+            "line 1: unknown interpolation: cubic"
+        );
+        assert_eq!(error("playback loop\n# nothing else"), "no keyframes");
+    }
+
+    #[test]
+    fn linear_between_keys_and_held_outside() {
+        let track = track(Interpolation::Linear, &[(1.0, 10.0), (3.0, 20.0)]);
+        assert_eq!(track.sample(0.0), Some(10.0));
+        assert_eq!(track.sample(2.0), Some(15.0));
+        assert_eq!(track.sample(5.0), Some(20.0));
+        assert_eq!(Track::<f32>::new(Interpolation::Linear).sample(1.0), None);
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_the_keys() {
+        let keys = [(0.0, 0.0), (1.0, 4.0), (2.0, -2.0), (3.0, 1.0)];
+        let track = track(Interpolation::CatmullRom, &keys);
+        for (time, value) in keys {
+            let sampled = track.sample(time).expect("has keys");
+            assert!(close(sampled, value), "{} at {}", sampled, time);
+        }
+        // Curves instead of going straight, overshooting the key at 1
+        let linear = lerp(4.0, -2.0, 0.25);
+        assert!(track.sample(1.25).expect("has keys") > linear);
+    }
+
+    #[test]
+    fn catmull_rom_ends_meet_the_end_keys() {
+        let track = track(
+            Interpolation::CatmullRom,
+            &[(0.0, 1.0), (1.0, 3.0), (2.0, 2.0)],
+        );
+        // Repeating the end keys for missing neighbours stays continuous
+        let start = track.sample(1e-4).expect("has keys");
+        let end = track.sample(2.0 - 1e-4).expect("has keys");
+        assert!((start - 1.0).abs() < 1e-3, "{}", start);
+        assert!((end - 2.0).abs() < 1e-3, "{}", end);
+        // And holds them outside
+        assert_eq!(track.sample(-1.0), Some(1.0));
+        assert_eq!(track.sample(3.0), Some(2.0));
+    }
+
+    #[test]
+    fn catmull_rom_with_a_single_key() {
+        let track = track(Interpolation::CatmullRom, &[(1.0, 5.0)]);
+        assert_eq!(track.sample(0.5), Some(5.0));
+        assert_eq!(track.sample(1.5), Some(5.0));
+    }
+
+    #[test]
+    fn ease_slows_into_and_out_of_keys() {
+        let track = track(Interpolation::Ease, &[(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(track.sample(0.0), Some(0.0));
+        assert_eq!(track.sample(1.0), Some(1.0));
+        assert!(close(track.sample(0.5).expect("has keys"), 0.5));
+        // Barely moving close to either key, compared to linear
+        assert!(track.sample(0.05).expect("has keys") < 0.01);
+        assert!(track.sample(0.95).expect("has keys") > 0.99);
+    }
+
+    fn path(playback: &str) -> CameraPath {
+        CameraPath::parse(&format!("playback {}\nfovy 0 40\nfovy 10 60", playback)).expect("valid")
+    }
+
+    #[test]
+    fn playback_after_the_end() {
+        assert_eq!(path("once").path_time(4.0), 4.0);
+        assert_eq!(path("once").path_time(25.0), 10.0);
+        assert_eq!(path("loop").path_time(4.0), 4.0);
+        assert_eq!(path("loop").path_time(14.0), 4.0);
+    }
+
+    #[test]
+    fn pingpong_turns_around_at_either_end() {
+        let path = path("pingpong");
+        assert_eq!(path.path_time(4.0), 4.0);
+        assert_eq!(path.path_time(10.0), 10.0);
+        // On the way back
+        assert_eq!(path.path_time(14.0), 6.0);
+        assert_eq!(path.path_time(20.0), 0.0);
+        // And forwards again
+        assert_eq!(path.path_time(24.0), 4.0);
+
+        let mut camera = Camera {
+            eye: (0.0, 1.0, 2.0).into(),
+            target: (0.0, 0.0, -100.0).into(),
+            up: Vector3::unit_y(),
+            aspect: 1.0,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 1000.0,
+        };
+        path.apply(&mut camera, 15.0);
+        assert_eq!(camera.fovy, 50.0);
+        // Only the tracks with keys move the camera
+        assert_eq!(camera.eye, Point3::new(0.0, 1.0, 2.0));
+    }
+}
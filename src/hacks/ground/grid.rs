@@ -0,0 +1,152 @@
+use wgpu::util::DeviceExt;
+use wgpu::RenderPass;
+
+use super::VELOCITY;
+use crate::args::Args;
+use crate::depth::depth_stencil_state;
+use crate::hacks::{Hack, HackContext, UpdateContext};
+use crate::params::parse_color;
+use crate::shader::{wgsl, ReloadablePipeline};
+
+/// Size of a grid cell.
+const CELL: f32 = 2.0;
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GridUniform {
+    color: [f32; 4],
+    /// Camera position, the plane is centered below it. `w` is unused.
+    eye: [f32; 4],
+    /// How far the grid has moved towards the camera, wraps around at `cell`
+    scroll: f32,
+    cell: f32,
+    // Uniforms are laid out in 16 byte chunks
+    _padding: [f32; 2],
+}
+
+/// An endless grid of lines on the floor, drawn in the fragment shader.
+pub struct Grid {
+    pipeline: ReloadablePipeline,
+    params: GridUniform,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    speed: f64,
+}
+
+impl Hack for Grid {
+    fn init(context: &HackContext, args: &Args) -> Self {
+        let device = context.device;
+        let color = parse_color(&args.text("color")).expect("validated when parsing");
+        let params = GridUniform {
+            color: [color[0], color[1], color[2], 1.0],
+            eye: [0.0; 4],
+            scroll: 0.0,
+            cell: CELL,
+            _padding: [0.0; 2],
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Grid Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("grid_params_bind_group_layout"),
+            });
+
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("grid_params_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                context.frame_bind_group_layout,
+                context.camera_bind_group_layout,
+                &params_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let (format, depth_format, sample_count) =
+            (context.format, context.depth_format, context.sample_count);
+        // The plane is generated from the vertex index, no buffers needed
+        let pipeline = ReloadablePipeline::new(
+            device,
+            wgsl!("grid.wgsl"),
+            context.reload_shaders,
+            move |device, shader| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Grid Pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: Some(depth_stencil_state(depth_format)),
+                    multisample: wgpu::MultisampleState {
+                        count: sample_count,
+                        ..wgpu::MultisampleState::default()
+                    },
+                    multiview: None,
+                })
+            },
+        );
+
+        Self {
+            pipeline,
+            params,
+            params_buffer,
+            params_bind_group,
+            speed: args.number("speed"),
+        }
+    }
+
+    fn update(&mut self, context: &mut UpdateContext) {
+        self.pipeline.reload(context.device);
+        // Moving one cell looks the same as not moving at all, so looping at
+        // that period is seamless whatever the frame rate
+        let period = CELL as f64 / (VELOCITY * self.speed);
+        self.params.scroll = context.clock.phase(period) * CELL;
+        let eye = context.camera.eye;
+        self.params.eye = [eye.x, eye.y, eye.z, 1.0];
+        context
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[self.params]));
+    }
+
+    fn render<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+        rpass.set_pipeline(self.pipeline.pipeline());
+        rpass.set_bind_group(2, &self.params_bind_group, &[]);
+        rpass.draw(0..6, 0..1);
+    }
+}
@@ -0,0 +1,75 @@
+struct FrameUniform {
+    time: f32,
+    delta: f32,
+    frame: u32,
+    resolution: vec2<f32>,
+};
+@group(0) @binding(0)
+var<uniform> frame: FrameUniform;
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct GridUniform {
+    color: vec4<f32>,
+    eye: vec4<f32>,
+    scroll: f32,
+    cell: f32,
+};
+@group(2) @binding(0)
+var<uniform> params: GridUniform;
+
+// Half the size of the plane we draw, it follows the camera around
+let EXTENT: f32 = 400.0;
+// Fraction of a cell covered by a line
+let LINE_WIDTH: f32 = 0.04;
+// Lines fade out between these distances from the camera
+let FOG_NEAR: f32 = 30.0;
+let FOG_FAR: f32 = 300.0;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world: vec3<f32>,
+};
+
+// Vertex Shader
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // Two triangles covering the plane below the camera
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[index] * EXTENT;
+    var out: VertexOutput;
+    out.world = vec3<f32>(params.eye.x + corner.x, 0.0, params.eye.z + corner.y);
+    out.clip_position = camera.view_proj * vec4<f32>(out.world, 1.0);
+    return out;
+}
+
+// Fragment Shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = vec2<f32>(in.world.x, in.world.z - params.scroll) / params.cell;
+    // How much of a cell a pixel covers, for antialiasing
+    let width = fwidth(uv);
+    // Distance to the closest line, in cells
+    let to_line = abs(fract(uv - 0.5) - 0.5);
+    let coverage = clamp((LINE_WIDTH * 0.5 - to_line) / width + 0.5, vec2<f32>(0.0), vec2<f32>(1.0));
+    var glow = max(coverage.x, coverage.y);
+    // Once cells are only a few pixels across the lines start to shimmer,
+    // use how much of a cell they cover on average instead
+    let density = LINE_WIDTH * (2.0 - LINE_WIDTH);
+    glow = mix(glow, density, smoothstep(0.2, 0.5, max(width.x, width.y)));
+
+    let fog = 1.0 - smoothstep(FOG_NEAR, FOG_FAR, distance(in.world.xz, params.eye.xz));
+    return vec4<f32>(params.color.rgb, glow * fog);
+}
@@ -0,0 +1,237 @@
+use wgpu::util::DeviceExt;
+use wgpu::RenderPass;
+
+use super::VELOCITY;
+use crate::args::Args;
+use crate::depth::depth_stencil_state;
+use crate::hacks::{Hack, HackContext, UpdateContext};
+use crate::params::parse_color;
+use crate::primitives::{Instance, InstanceBuffer, InstanceRaw, Vertex};
+use crate::shader::{wgsl, ReloadablePipeline};
+
+const VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-100.0, 0.0, 0.1],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [100.0, 0.0, 0.1],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [100.0, 0.0, -0.1],
+        color: [0.0, 0.0, 1.0],
+    },
+    Vertex {
+        position: [-100.0, 0.0, -0.1],
+        color: [0.0, 0.0, 1.0],
+    },
+];
+
+const INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+
+/// Depth covered by the lines, they're spread evenly across it.
+const DEPTH: f32 = 30.0;
+
+/// A repeatable pseudo random number in 0..1 for `i`, so lines look the
+/// same every run.
+fn hash(i: u32) -> f32 {
+    let mut x = i.wrapping_mul(0x9e37_79b9) ^ 0x85eb_ca6b;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x as f32 / u32::MAX as f32
+}
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LinesUniform {
+    color: [f32; 4],
+    /// How much the lines pulse, 0 keeps them steady
+    variation: f32,
+    // Uniforms are laid out in 16 byte chunks
+    _padding: [f32; 3],
+}
+
+/// Horizontal bars sliding towards the camera.
+pub struct Lines {
+    pipeline: ReloadablePipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    instances: Vec<Instance>,
+    instance_buffer: InstanceBuffer,
+    _params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    speed: f64,
+    spacing: f64,
+}
+
+impl Hack for Lines {
+    fn init(context: &HackContext, args: &Args) -> Self {
+        let device = context.device;
+        let color = parse_color(&args.text("color")).expect("validated when parsing");
+        let params = LinesUniform {
+            color: [color[0], color[1], color[2], 1.0],
+            variation: args.number("variation") as f32,
+            _padding: [0.0; 3],
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lines Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("lines_params_bind_group_layout"),
+            });
+
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("lines_params_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                context.frame_bind_group_layout,
+                context.camera_bind_group_layout,
+                &params_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let (format, depth_format, sample_count) =
+            (context.format, context.depth_format, context.sample_count);
+        let pipeline = ReloadablePipeline::new(
+            device,
+            wgsl!("lines.wgsl"),
+            context.reload_shaders,
+            move |device, shader| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: None,
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: shader,
+                        entry_point: "vs_main",
+                        buffers: &[Vertex::desc(), InstanceRaw::desc()],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: Some(depth_stencil_state(depth_format)),
+                    multisample: wgpu::MultisampleState {
+                        count: sample_count,
+                        ..wgpu::MultisampleState::default()
+                    },
+                    multiview: None,
+                })
+            },
+        );
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let num_instances = args.number("count") as u32;
+        let variation = params.variation;
+        let instances = (0..num_instances)
+            .map(move |i| {
+                let position = cgmath::Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: -(i as f32 / num_instances as f32 * DEPTH),
+                };
+                // Each line gets its own thickness, shade and pulse timing
+                let (a, b, c) = (hash(i * 3), hash(i * 3 + 1), hash(i * 3 + 2));
+                let thickness = 1.0 + variation * (a * 2.0 - 0.5);
+                let shade = 1.0 - variation * 0.6 * b;
+                Instance {
+                    position,
+                    scale: cgmath::Vector3::new(1.0, 1.0, thickness),
+                    color: [shade, 1.0 - variation * 0.6 * (1.0 - b), 1.0, 1.0],
+                    phase: c * std::f32::consts::TAU,
+                    ..Instance::default()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let instance_buffer = InstanceBuffer::new(device, instances.len());
+        Self {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: INDICES.len() as u32,
+            instances,
+            instance_buffer,
+            _params_buffer: params_buffer,
+            params_bind_group,
+            speed: args.number("speed"),
+            spacing: (DEPTH / num_instances as f32) as f64,
+        }
+    }
+
+    fn update(&mut self, context: &mut UpdateContext) {
+        self.pipeline.reload(context.device);
+        // Once a line passes the camera it goes back behind the others,
+        // keeping its own looks, so the set loops seamlessly after moving
+        // the whole depth
+        let period = DEPTH as f64 / (VELOCITY * self.speed);
+        let offset = context.clock.phase(period) * DEPTH;
+        let spacing = self.spacing as f32;
+        for (i, instance) in self.instances.iter_mut().enumerate() {
+            let z = offset - i as f32 * spacing + DEPTH - spacing;
+            instance.position.z = z.rem_euclid(DEPTH) - (DEPTH - spacing);
+        }
+        let instance_data = self
+            .instances
+            .iter()
+            .map(Instance::to_raw)
+            .collect::<Vec<_>>();
+        self.instance_buffer
+            .write(context.device, context.queue, &instance_data);
+    }
+
+    fn render<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+        rpass.set_pipeline(self.pipeline.pipeline());
+        rpass.set_bind_group(2, &self.params_bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.set_vertex_buffer(1, self.instance_buffer.slice());
+        rpass.draw_indexed(0..self.num_indices, 0, 0..self.instance_buffer.count());
+    }
+}
@@ -3,20 +3,33 @@ struct InstanceInput {
     @location(6) model_matrix_1: vec4<f32>,
     @location(7) model_matrix_2: vec4<f32>,
     @location(8) model_matrix_3: vec4<f32>,
+    @location(9) color: vec4<f32>,
+    @location(10) phase: f32,
 };
 
 struct FrameUniform {
+    time: f32,
+    delta: f32,
     frame: u32,
+    resolution: vec2<f32>,
 };
 @group(0) @binding(0)
 var<uniform> frame: FrameUniform;
 
 struct CameraUniform {
     view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
 };
 @group(1) @binding(0)
 var<uniform> camera: CameraUniform;
 
+struct LinesUniform {
+    color: vec4<f32>,
+    variation: f32,
+};
+@group(2) @binding(0)
+var<uniform> params: LinesUniform;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
@@ -26,6 +39,7 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
     @location(1) position: vec3<f32>,
+    @location(2) tint: vec4<f32>,
 };
 
 // Vertex Shader
@@ -42,8 +56,9 @@ fn vs_main(
         instance.model_matrix_3,
     );
     out.color = model.color;
-    let offset = vec3<f32>(0.0, 0.0, f32(frame.frame) * (0.03 * 4.0));
-    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position + offset, 1.0);
+    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+    let pulse = 1.0 - params.variation * 0.5 * (0.5 + 0.5 * sin(frame.time * 3.0 + instance.phase));
+    out.tint = vec4<f32>(instance.color.rgb * pulse, instance.color.a);
     out.position = out.clip_position.xyz;
     return out;
 }
@@ -51,5 +66,5 @@ fn vs_main(
 // Fragment Shader
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(1.0, 0.1, 1.0, 1.0 - ((in.position.z - 10.0) * 0.1));
+    return vec4<f32>(params.color.rgb * in.tint.rgb, in.tint.a * (1.0 - ((in.position.z - 10.0) * 0.1)));
 }
@@ -0,0 +1,124 @@
+//! A glowing floor scrolling towards the camera, in a few styles.
+
+use crate::args::Args;
+use crate::hacks::{Hack, HackContext};
+use crate::params::{Kind, Parameter};
+
+mod grid;
+mod lines;
+mod terrain;
+
+pub use grid::Grid;
+pub use lines::Lines;
+pub use terrain::Terrain;
+
+/// Units per second at speed 1.
+const VELOCITY: f64 = 3.6;
+
+pub const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        id: "style",
+        label: "Ground style",
+        arg: "-style",
+        kind: Kind::Select {
+            options: &[("grid", "Grid"), ("lines", "Lines"), ("terrain", "Terrain")],
+            default: "grid",
+        },
+    },
+    Parameter {
+        id: "count",
+        label: "Line count (lines only)",
+        arg: "-count",
+        kind: Kind::Spin {
+            low: 1.0,
+            high: 200.0,
+            default: 20.0,
+        },
+    },
+    Parameter {
+        id: "speed",
+        label: "Speed",
+        arg: "-speed",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 4.0,
+            default: 1.0,
+            low_label: "Slow",
+            high_label: "Fast",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "color",
+        label: "Line color",
+        arg: "-color",
+        kind: Kind::Color { default: "#ff1aff" },
+    },
+    Parameter {
+        id: "variation",
+        label: "Line variation (lines only)",
+        arg: "-variation",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 1.0,
+            default: 0.0,
+            low_label: "Identical",
+            high_label: "Varied",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "terrainmesh",
+        label: "Terrain mesh (terrain only)",
+        arg: "-terrain-mesh",
+        kind: Kind::Select {
+            options: &[("wireframe", "Wireframe"), ("filled", "Filled")],
+            default: "wireframe",
+        },
+    },
+    Parameter {
+        id: "amplitude",
+        label: "Mountain height (terrain only)",
+        arg: "-amplitude",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 40.0,
+            default: 14.0,
+            low_label: "Flat",
+            high_label: "Tall",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "frequency",
+        label: "Mountain frequency (terrain only)",
+        arg: "-frequency",
+        kind: Kind::Slider {
+            low: 0.005,
+            high: 0.2,
+            default: 0.04,
+            low_label: "Rolling",
+            high_label: "Jagged",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "seed",
+        label: "Terrain seed (terrain only)",
+        arg: "-seed",
+        kind: Kind::Spin {
+            low: 0.0,
+            high: 99999.0,
+            default: 1.0,
+        },
+    },
+];
+
+/// The ground in the style picked with `-style`.
+pub fn create(context: &HackContext, args: &Args) -> Box<dyn Hack> {
+    match args.text("style").as_str() {
+        "lines" => Box::new(Lines::init(context, args)),
+        "terrain" => Box::new(Terrain::init(context, args)),
+        _ => Box::new(Grid::init(context, args)),
+    }
+}
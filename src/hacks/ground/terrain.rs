@@ -0,0 +1,483 @@
+use wgpu::util::DeviceExt;
+use wgpu::RenderPass;
+
+use super::VELOCITY;
+use crate::args::Args;
+use crate::depth::depth_stencil_state;
+use crate::hacks::{Hack, HackContext, UpdateContext};
+use crate::params::parse_color;
+use crate::primitives::{Instance, InstanceBuffer, InstanceRaw};
+use crate::shader::{wgsl, ReloadablePipeline};
+
+/// Distance between vertices.
+const CELL: f32 = 2.0;
+/// Cells from the center to either side.
+const HALF_WIDTH: u32 = 40;
+/// Cells along a chunk, front to back.
+const CHUNK_CELLS: u32 = 16;
+const CHUNK_DEPTH: f64 = CHUNK_CELLS as f64 * CELL as f64;
+/// Chunks kept around, enough to reach the fog with one behind the camera.
+const CHUNKS: usize = 12;
+/// Half the width of the flat valley in the middle.
+const VALLEY: f64 = 6.0;
+/// Distance over which the valley floor rises into the mountains.
+const SLOPE: f64 = 20.0;
+
+const COLUMNS: u32 = HALF_WIDTH * 2 + 1;
+const ROWS: u32 = CHUNK_CELLS + 1;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TerrainVertex {
+    position: [f32; 3],
+    /// Position in cells, for drawing the mesh edges in the filled mesh.
+    cell: [f32; 2],
+}
+
+impl TerrainVertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TerrainVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ],
+        }
+    }
+}
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TerrainUniform {
+    color: [f32; 4],
+    /// Camera position, for the fog. `w` is unused.
+    eye: [f32; 4],
+    amplitude: f32,
+    // Uniforms are laid out in 16 byte chunks
+    _padding: [f32; 3],
+}
+
+/// Seeded value noise with a few octaves, roughly in 0..1.
+struct Noise {
+    seed: u32,
+}
+
+impl Noise {
+    /// A repeatable pseudo random number in 0..1 for a lattice point.
+    fn hash(&self, x: i64, z: i64) -> f64 {
+        let mut h = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ (z as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+            ^ (self.seed as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        h ^= h >> 33;
+        h as f64 / u64::MAX as f64
+    }
+
+    fn value(&self, x: f64, z: f64) -> f64 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (tx, tz) = (x - x0, z - z0);
+        // Smoothstep, so there are no creases along the lattice
+        let (sx, sz) = (tx * tx * (3.0 - 2.0 * tx), tz * tz * (3.0 - 2.0 * tz));
+        let (ix, iz) = (x0 as i64, z0 as i64);
+        let top = self.hash(ix, iz) * (1.0 - sx) + self.hash(ix + 1, iz) * sx;
+        let bottom = self.hash(ix, iz + 1) * (1.0 - sx) + self.hash(ix + 1, iz + 1) * sx;
+        top * (1.0 - sz) + bottom * sz
+    }
+
+    fn fbm(&self, x: f64, z: f64) -> f64 {
+        let (mut sum, mut amplitude, mut frequency, mut total) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..4 {
+            sum += self.value(x * frequency, z * frequency) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / total
+    }
+}
+
+/// A stretch of terrain `CHUNK_DEPTH` deep, `index` chunks ahead of where we
+/// started.
+struct Chunk {
+    index: i64,
+    vertex_buffer: wgpu::Buffer,
+}
+
+/// Mountains on either side of a flat valley, generated in chunks as we
+/// travel along it.
+pub struct Terrain {
+    pipeline: ReloadablePipeline,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    chunks: Vec<Chunk>,
+    instances: Vec<Instance>,
+    instance_buffer: InstanceBuffer,
+    params: TerrainUniform,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    landscape: Landscape,
+    speed: f64,
+}
+
+/// The shape of the valley and its mountains.
+struct Landscape {
+    noise: Noise,
+    frequency: f64,
+    /// Height of the tallest mountains.
+    amplitude: f64,
+}
+
+impl Landscape {
+    /// Vertices of the chunk `index`, heights worked out in travel
+    /// distance so neighbouring chunks meet exactly.
+    fn vertices(&self, index: i64) -> Vec<TerrainVertex> {
+        let amplitude = self.amplitude;
+        let mut vertices = Vec::with_capacity((COLUMNS * ROWS) as usize);
+        for row in 0..ROWS {
+            let distance = index as f64 * CHUNK_DEPTH + (row as f64 * CELL as f64);
+            for column in 0..COLUMNS {
+                let x = (column as f64 - HALF_WIDTH as f64) * CELL as f64;
+                let t = ((x.abs() - VALLEY) / SLOPE).clamp(0.0, 1.0);
+                let rise = t * t * (3.0 - 2.0 * t);
+                let height = amplitude
+                    * rise
+                    * self
+                        .noise
+                        .fbm(x * self.frequency, distance * self.frequency);
+                vertices.push(TerrainVertex {
+                    position: [x as f32, height as f32, -(row as f32 * CELL)],
+                    cell: [column as f32, row as f32],
+                });
+            }
+        }
+        vertices
+    }
+}
+
+/// Indices joining the vertices of a chunk into lines or triangles.
+fn indices(wireframe: bool) -> Vec<u16> {
+    let vertex = |column: u32, row: u32| (row * COLUMNS + column) as u16;
+    let mut indices = Vec::new();
+    for row in 0..ROWS {
+        for column in 0..COLUMNS {
+            let right = column + 1 < COLUMNS;
+            let back = row + 1 < ROWS;
+            if wireframe {
+                if right {
+                    indices.extend([vertex(column, row), vertex(column + 1, row)]);
+                }
+                if back {
+                    indices.extend([vertex(column, row), vertex(column, row + 1)]);
+                }
+            } else if right && back {
+                indices.extend([
+                    vertex(column, row),
+                    vertex(column + 1, row),
+                    vertex(column + 1, row + 1),
+                    vertex(column, row),
+                    vertex(column + 1, row + 1),
+                    vertex(column, row + 1),
+                ]);
+            }
+        }
+    }
+    indices
+}
+
+impl Hack for Terrain {
+    fn init(context: &HackContext, args: &Args) -> Self {
+        let device = context.device;
+        let wireframe = args.text("terrainmesh") == "wireframe";
+        let color = parse_color(&args.text("color")).expect("validated when parsing");
+        let params = TerrainUniform {
+            color: [color[0], color[1], color[2], 1.0],
+            eye: [0.0; 4],
+            amplitude: args.number("amplitude") as f32,
+            _padding: [0.0; 3],
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("terrain_params_bind_group_layout"),
+            });
+
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("terrain_params_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                context.frame_bind_group_layout,
+                context.camera_bind_group_layout,
+                &params_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let (format, depth_format, sample_count) =
+            (context.format, context.depth_format, context.sample_count);
+        let pipeline = ReloadablePipeline::new(
+            device,
+            wgsl!("terrain.wgsl"),
+            context.reload_shaders,
+            move |device, shader| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Terrain Pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: shader,
+                        entry_point: "vs_main",
+                        buffers: &[TerrainVertex::desc(), InstanceRaw::desc()],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: shader,
+                        entry_point: if wireframe {
+                            "fs_wireframe"
+                        } else {
+                            "fs_filled"
+                        },
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: if wireframe {
+                            wgpu::PrimitiveTopology::LineList
+                        } else {
+                            wgpu::PrimitiveTopology::TriangleList
+                        },
+                        ..wgpu::PrimitiveState::default()
+                    },
+                    depth_stencil: Some(depth_stencil_state(depth_format)),
+                    multisample: wgpu::MultisampleState {
+                        count: sample_count,
+                        ..wgpu::MultisampleState::default()
+                    },
+                    multiview: None,
+                })
+            },
+        );
+
+        let indices = indices(wireframe);
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // Filled in by the first update, `index` can't match any chunk we
+        // want so they all get generated
+        let chunks = (0..CHUNKS)
+            .map(|_| Chunk {
+                index: i64::MIN,
+                vertex_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Terrain Chunk Buffer"),
+                    size: (COLUMNS * ROWS) as wgpu::BufferAddress
+                        * std::mem::size_of::<TerrainVertex>() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+            })
+            .collect();
+
+        Self {
+            pipeline,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            chunks,
+            instances: (0..CHUNKS).map(|_| Instance::default()).collect(),
+            instance_buffer: InstanceBuffer::new(device, CHUNKS),
+            params,
+            params_buffer,
+            params_bind_group,
+            landscape: Landscape {
+                noise: Noise {
+                    seed: args.number("seed") as u32,
+                },
+                frequency: args.number("frequency"),
+                amplitude: args.number("amplitude"),
+            },
+            speed: args.number("speed"),
+        }
+    }
+
+    fn update(&mut self, context: &mut UpdateContext) {
+        self.pipeline.reload(context.device);
+        let travelled = context.clock.elapsed() * VELOCITY * self.speed;
+        // Streamed around the eye rather than the start, a camera path may
+        // take it anywhere along the valley. One chunk behind it, the rest
+        // ahead.
+        let eye = context.camera.eye;
+        let first = ((travelled - eye.z as f64) / CHUNK_DEPTH).floor() as i64 - 1;
+        let wanted = first..first + CHUNKS as i64;
+
+        // Chunks we've left behind are regenerated at the far end
+        let mut missing = wanted
+            .clone()
+            .filter(|index| !self.chunks.iter().any(|chunk| chunk.index == *index))
+            .collect::<Vec<_>>()
+            .into_iter();
+        for i in 0..self.chunks.len() {
+            if wanted.contains(&self.chunks[i].index) {
+                continue;
+            }
+            let index = missing.next().expect("a chunk for every free buffer");
+            let vertices = self.landscape.vertices(index);
+            let chunk = &mut self.chunks[i];
+            chunk.index = index;
+            context
+                .queue
+                .write_buffer(&chunk.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+
+        for (chunk, instance) in self.chunks.iter().zip(self.instances.iter_mut()) {
+            // Worked out in f64, `travelled` grows without bound
+            instance.position.z = (travelled - chunk.index as f64 * CHUNK_DEPTH) as f32;
+        }
+        let instance_data = self
+            .instances
+            .iter()
+            .map(Instance::to_raw)
+            .collect::<Vec<_>>();
+        self.instance_buffer
+            .write(context.device, context.queue, &instance_data);
+
+        self.params.eye = [eye.x, eye.y, eye.z, 1.0];
+        context
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[self.params]));
+    }
+
+    fn render<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+        rpass.set_pipeline(self.pipeline.pipeline());
+        rpass.set_bind_group(2, &self.params_bind_group, &[]);
+        rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.set_vertex_buffer(1, self.instance_buffer.slice());
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            let i = i as u32;
+            rpass.set_vertex_buffer(0, chunk.vertex_buffer.slice(..));
+            rpass.draw_indexed(0..self.num_indices, 0, i..i + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn landscape(seed: u32) -> Landscape {
+        Landscape {
+            noise: Noise { seed },
+            frequency: 0.05,
+            amplitude: 10.0,
+        }
+    }
+
+    fn heights(vertices: &[TerrainVertex]) -> Vec<f32> {
+        vertices.iter().map(|vertex| vertex.position[1]).collect()
+    }
+
+    #[test]
+    fn seeds_decide_the_heights() {
+        assert_eq!(
+            heights(&landscape(1).vertices(3)),
+            heights(&landscape(1).vertices(3))
+        );
+        assert_ne!(
+            heights(&landscape(1).vertices(3)),
+            heights(&landscape(2).vertices(3))
+        );
+    }
+
+    #[test]
+    fn the_valley_is_flat_and_the_sides_rise() {
+        let vertices = landscape(7).vertices(0);
+        let valley = vertices
+            .iter()
+            .filter(|vertex| (vertex.position[0].abs() as f64) <= VALLEY);
+        assert!(valley.clone().count() > 0);
+        assert!(valley.clone().all(|vertex| vertex.position[1] == 0.0));
+
+        // Averaged along the chunk, the outermost columns stand well above
+        // the foot of the slope, and no higher than the amplitude
+        let column_height = |column: u32| {
+            (0..ROWS)
+                .map(|row| vertices[(row * COLUMNS + column) as usize].position[1])
+                .sum::<f32>()
+                / ROWS as f32
+        };
+        let edge = column_height(0).min(column_height(COLUMNS - 1));
+        assert!(edge > 0.0);
+        assert!(edge <= 10.0);
+        let slope_start = HALF_WIDTH + (VALLEY / CELL as f64).ceil() as u32 + 1;
+        assert!(column_height(slope_start) < edge);
+    }
+
+    #[test]
+    fn neighbouring_chunks_meet_without_a_seam() {
+        let landscape = landscape(7);
+        let (near, far) = (landscape.vertices(4), landscape.vertices(5));
+        let last_row = &near[((ROWS - 1) * COLUMNS) as usize..];
+        let first_row = &far[..COLUMNS as usize];
+        for (back, front) in last_row.iter().zip(first_row) {
+            assert_eq!(back.position[0], front.position[0]);
+            assert_eq!(back.position[1], front.position[1]);
+        }
+        // Chunk 5 starts a chunk further along, where chunk 4 ends
+        assert_eq!(-last_row[0].position[2] as f64, CHUNK_DEPTH);
+        assert_eq!(first_row[0].position[2], 0.0);
+    }
+
+    #[test]
+    fn wireframe_indices_are_lines_between_neighbours() {
+        let indices = indices(true);
+        let edges = ROWS * (COLUMNS - 1) + COLUMNS * (ROWS - 1);
+        assert_eq!(indices.len(), 2 * edges as usize);
+        assert!(indices.iter().all(|&i| (i as u32) < COLUMNS * ROWS));
+    }
+
+    #[test]
+    fn filled_indices_are_two_triangles_a_cell() {
+        let indices = indices(false);
+        let cells = (COLUMNS - 1) * (ROWS - 1);
+        assert_eq!(indices.len(), 6 * cells as usize);
+        assert!(indices.iter().all(|&i| (i as u32) < COLUMNS * ROWS));
+    }
+}
@@ -0,0 +1,82 @@
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+};
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct TerrainUniform {
+    color: vec4<f32>,
+    eye: vec4<f32>,
+    amplitude: f32,
+};
+@group(2) @binding(0)
+var<uniform> params: TerrainUniform;
+
+// Fraction of a cell covered by a line in the filled mesh
+let LINE_WIDTH: f32 = 0.05;
+// The terrain fades out between these distances from the camera
+let FOG_NEAR: f32 = 30.0;
+let FOG_FAR: f32 = 300.0;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) cell: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world: vec3<f32>,
+    @location(1) cell: vec2<f32>,
+};
+
+// Vertex Shader
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    // One instance per chunk, placing it relative to the camera
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    var out: VertexOutput;
+    let world = model_matrix * vec4<f32>(model.position, 1.0);
+    out.world = world.xyz;
+    out.cell = model.cell;
+    out.clip_position = camera.view_proj * world;
+    return out;
+}
+
+fn fog(world: vec3<f32>) -> f32 {
+    return 1.0 - smoothstep(FOG_NEAR, FOG_FAR, distance(world.xz, params.eye.xz));
+}
+
+// Fragment Shaders
+@fragment
+fn fs_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(params.color.rgb, fog(in.world));
+}
+
+@fragment
+fn fs_filled(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Mesh edges drawn like the grid, antialiased with derivatives
+    let width = fwidth(in.cell);
+    let to_line = abs(fract(in.cell - 0.5) - 0.5);
+    let coverage = clamp((LINE_WIDTH * 0.5 - to_line) / width + 0.5, vec2<f32>(0.0), vec2<f32>(1.0));
+    let edge = max(coverage.x, coverage.y);
+    // Peaks are lit a little by the lines' glow, valleys stay dark
+    let height = clamp(in.world.y / max(params.amplitude, 0.001), 0.0, 1.0);
+    let fill = params.color.rgb * (0.03 + 0.12 * height);
+    return vec4<f32>(mix(fill, params.color.rgb, edge), fog(in.world));
+}
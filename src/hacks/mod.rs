@@ -0,0 +1,97 @@
+use wgpu::{BindGroupLayout, CommandEncoder, Device, Queue, RenderPass, TextureFormat};
+
+use crate::args::Args;
+use crate::params::Parameter;
+use crate::primitives::{Camera, Clock};
+use crate::xscreensaver::Event;
+
+mod ground;
+mod shadertoy;
+mod slideshow;
+
+/// `(name, label)` of every hack, `name` being what `-hack` takes.
+pub const HACKS: &[(&str, &str)] = &[
+    ("ground", "Ground"),
+    ("shadertoy", "Shadertoy"),
+    ("slideshow", "Slideshow"),
+];
+
+/// Everything a hack needs from the renderer to build its pipelines.
+pub struct HackContext<'a> {
+    pub device: &'a Device,
+    /// Format of the color target hacks render into.
+    pub format: TextureFormat,
+    /// Size of the color target in pixels, until the hack is resized.
+    pub width: u32,
+    pub height: u32,
+    /// The main pass has a depth attachment of this format, pipelines
+    /// drawing in it need a matching depth stencil state.
+    pub depth_format: TextureFormat,
+    /// MSAA samples of the color and depth attachments of the main pass.
+    pub sample_count: u32,
+    /// Whether `Rgba32Float` textures can be sampled with linear filtering.
+    pub float32_filterable: bool,
+    /// Layout of the `FrameUniform` bound at group 0.
+    pub frame_bind_group_layout: &'a BindGroupLayout,
+    /// Layout of the camera uniform bound at group 1.
+    pub camera_bind_group_layout: &'a BindGroupLayout,
+    /// Whether pipelines should follow changes to their shaders on disk,
+    /// see [`crate::shader`].
+    pub reload_shaders: bool,
+}
+
+/// What a hack gets to look at, and change, every frame.
+pub struct UpdateContext<'a> {
+    /// For hacks whose buffers need to grow.
+    pub device: &'a Device,
+    pub queue: &'a Queue,
+    pub clock: &'a Clock,
+    /// Hacks are free to move the camera, the uniform follows.
+    pub camera: &'a mut Camera,
+    /// Window events since the last frame.
+    pub events: &'a [Event],
+}
+
+/// A screensaver `State` can host.
+pub trait Hack {
+    fn init(context: &HackContext, args: &Args) -> Self
+    where
+        Self: Sized;
+
+    /// Called once per frame before rendering.
+    fn update(&mut self, _context: &mut UpdateContext) {}
+
+    /// Render whatever the main pass needs from this frame, like offscreen
+    /// buffers, before it starts.
+    fn prepare(&mut self, _device: &Device, _encoder: &mut CommandEncoder) {}
+
+    /// Draw the hack. Groups 0 and 1 are already bound to the frame and
+    /// camera uniforms, hacks use group 2 onwards for their own.
+    fn render<'a>(&'a self, rpass: &mut RenderPass<'a>);
+
+    fn resize(&mut self, _device: &Device, _width: u32, _height: u32) {}
+
+    /// Whether the sky is drawn behind the hack. Hacks covering the whole
+    /// screen themselves have no use for it.
+    fn wants_sky(&self) -> bool {
+        true
+    }
+}
+
+/// Parameters of every hack.
+pub fn parameters() -> impl Iterator<Item = &'static Parameter> {
+    ground::PARAMETERS
+        .iter()
+        .chain(shadertoy::PARAMETERS)
+        .chain(slideshow::PARAMETERS)
+}
+
+/// Create the hack called `name`, one of [`HACKS`].
+pub fn create(name: &str, context: &HackContext, args: &Args) -> Box<dyn Hack> {
+    match name {
+        "ground" => ground::create(context, args),
+        "shadertoy" => Box::new(shadertoy::Shadertoy::init(context, args)),
+        "slideshow" => Box::new(slideshow::Slideshow::init(context, args)),
+        _ => panic!("no hack called {}", name),
+    }
+}
@@ -0,0 +1,625 @@
+//! Runs a Shadertoy style fragment shader over the whole screen.
+//!
+//! The shader is a file with a `mainImage` in GLSL, as on Shadertoy:
+//!
+//! ```glsl
+//! void mainImage(out vec4 fragColor, in vec2 fragCoord)
+//! ```
+//!
+//! or in WGSL, with the inputs in `shadertoy`, like `shadertoy.iTime`:
+//!
+//! ```wgsl
+//! fn mainImage(fragCoord: vec2<f32>) -> vec4<f32>
+//! ```
+//!
+//! Either gets `iResolution`, `iTime`, `iTimeDelta`, `iFrame` and `iMouse`,
+//! and `iChannel0..3` to read the buffers of a multipass shader from. In
+//! WGSL those are textures, to be sampled with `channel_sampler`.
+//!
+//! A multipass shader is a manifest ending in `.passes` listing the shaders
+//! for the image and for up to four offscreen buffers, see [`Passes`].
+//! Buffers are `Rgba32Float`, like Shadertoy's. Adapters that can't filter
+//! that, llvmpipe among them, read them from the nearest pixel instead.
+
+use std::path::Path;
+use wgpu::util::DeviceExt;
+use wgpu::{
+    BindGroup, BindGroupLayout, CommandEncoder, Device, PipelineLayout, RenderPass, RenderPipeline,
+    Sampler, ShaderModule, TextureFormat, TextureView,
+};
+
+use crate::args::Args;
+use crate::hacks::{Hack, HackContext, UpdateContext};
+use crate::params::{Kind, Parameter};
+use crate::post::RenderTexture;
+use crate::shader::{self, Language};
+use crate::xscreensaver::Event;
+
+mod passes;
+
+use passes::{Passes, BUFFERS, CHANNELS};
+
+pub const PARAMETERS: &[Parameter] = &[Parameter {
+    id: "shader",
+    label: "Shader file, GLSL, .wgsl or .passes (shadertoy only)",
+    arg: "-shader",
+    kind: Kind::File,
+}];
+
+/// Where `mainImage` goes in the wrappers.
+const MAIN_IMAGE: &str = "// mainImage\n";
+/// Buffers keep full floats, like Shadertoy's, for shaders that carry state
+/// like positions from frame to frame.
+const BUFFER_FORMAT: TextureFormat = TextureFormat::Rgba32Float;
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadertoyUniform {
+    /// Size of the screen in pixels, `z` is the pixel aspect ratio
+    resolution: [f32; 3],
+    time: f32,
+    time_delta: f32,
+    frame: i32,
+    // vec4 is aligned to 16 bytes
+    _padding: [f32; 2],
+    /// See [`Mouse::uniform`]
+    mouse: [f32; 4],
+}
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PassUniform {
+    /// Whether `fragCoord` counts up from the bottom of the target. The
+    /// screen does, like Shadertoy, but buffers count from the top so
+    /// texture coordinates read them the right way up.
+    flip: u32,
+    // Uniforms are laid out in 16 byte chunks
+    _padding: [u32; 3],
+}
+
+/// The pointer as Shadertoy's `iMouse` sees it.
+#[derive(Debug, Default)]
+struct Mouse {
+    /// Where the pointer was last dragged to.
+    position: [f32; 2],
+    /// Where the button was last pressed.
+    click: [f32; 2],
+    down: bool,
+    /// Whether the button was pressed this frame.
+    clicked: bool,
+}
+
+impl Mouse {
+    /// Follow `events` in a window `height` pixels high, flipping `y` to
+    /// Shadertoy's origin in the bottom left.
+    fn update(&mut self, events: &[Event], height: f32) {
+        self.clicked = false;
+        for event in events {
+            match *event {
+                Event::ButtonPressed { x, y } => {
+                    self.click = [x as f32, height - y as f32];
+                    self.position = self.click;
+                    self.down = true;
+                    self.clicked = true;
+                }
+                Event::PointerMoved { x, y } if self.down => {
+                    self.position = [x as f32, height - y as f32];
+                }
+                Event::ButtonReleased { x, y } if self.down => {
+                    self.position = [x as f32, height - y as f32];
+                    self.down = false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// `xy` is the drag position, `zw` the click position with `z` negative
+    /// once the button is up and `w` negative after the frame of the click.
+    fn uniform(&self) -> [f32; 4] {
+        let sign = |positive| if positive { 1.0 } else { -1.0 };
+        [
+            self.position[0],
+            self.position[1],
+            self.click[0] * sign(self.down),
+            self.click[1] * sign(self.clicked),
+        ]
+    }
+}
+
+/// One of the offscreen buffers A to D.
+struct Buffer {
+    pipeline: RenderPipeline,
+    channels: [Option<usize>; CHANNELS],
+    /// Written and read turn about, `front` is the one with the latest frame
+    textures: [RenderTexture; 2],
+    front: usize,
+}
+
+/// A full-screen fragment shader in the style of Shadertoy, maybe with
+/// buffers rendered before it.
+pub struct Shadertoy {
+    image: RenderPipeline,
+    image_channels: [Option<usize>; CHANNELS],
+    /// Made again every frame, the textures it reads change
+    image_bind_group: BindGroup,
+    /// Indexed by buffer, `None` for the ones not used.
+    buffers: Vec<Option<Buffer>>,
+    layout: BindGroupLayout,
+    /// For the frame and camera groups buffer passes don't have.
+    empty_bind_group: BindGroup,
+    sampler: Sampler,
+    /// Read by channels without a buffer.
+    black: RenderTexture,
+    params: ShadertoyUniform,
+    params_buffer: wgpu::Buffer,
+    /// A [`PassUniform`] for the image and one for buffers.
+    image_pass: wgpu::Buffer,
+    buffer_pass: wgpu::Buffer,
+    mouse: Mouse,
+}
+
+/// `mainImage` in `source` wrapped into a fragment shader, and how many
+/// lines come before it.
+fn wrap(language: Language, source: &str) -> (String, u32) {
+    let wrapper = match language {
+        Language::Wgsl => include_str!("wrapper.wgsl"),
+        Language::Glsl(_) => include_str!("wrapper.glsl"),
+    };
+    let (before, after) = wrapper
+        .split_once(MAIN_IMAGE)
+        .expect("the wrapper has a place for mainImage");
+    let lines = before.lines().count() as u32;
+    (format!("{}{}\n{}", before, source, after), lines)
+}
+
+/// GLSL unless the file ends in `.wgsl`.
+fn language(path: &Path) -> Language {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("wgsl") => Language::Wgsl,
+        _ => Language::Glsl(naga::ShaderStage::Fragment),
+    }
+}
+
+/// Build the pipeline running `mainImage` in `source`, read from `path`.
+fn compile(
+    device: &Device,
+    path: &Path,
+    source: &str,
+    build: &dyn Fn(&Device, &ShaderModule) -> RenderPipeline,
+) -> Result<RenderPipeline, String> {
+    let language = language(path);
+    let (source, prelude_lines) = wrap(language, source);
+    let module = shader::parse(language, &source, path, prelude_lines)?;
+    shader::create_pipeline(device, module, build)
+}
+
+/// [`compile`] the file at `path`.
+fn compile_file(
+    device: &Device,
+    path: &Path,
+    build: &dyn Fn(&Device, &ShaderModule) -> RenderPipeline,
+) -> Result<RenderPipeline, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    compile(device, path, &source, build)
+}
+
+/// A buffer with the [`PassUniform`] for the screen, with `flip`, or buffers.
+fn pass_buffer(device: &Device, flip: bool) -> wgpu::Buffer {
+    let pass = PassUniform {
+        flip: flip as u32,
+        _padding: [0; 3],
+    };
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Shadertoy Pass Buffer"),
+        contents: bytemuck::cast_slice(&[pass]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    })
+}
+
+/// The passes of a shader, and a pipeline for each.
+struct Compiled {
+    passes: Passes,
+    image: RenderPipeline,
+    /// Indexed by buffer, like `passes.buffers`.
+    buffers: Vec<Option<RenderPipeline>>,
+}
+
+/// Compile the shader, or every shader in the manifest, at `path`.
+fn load(
+    device: &Device,
+    path: &Path,
+    build_image: &dyn Fn(&Device, &ShaderModule) -> RenderPipeline,
+    build_buffer: &dyn Fn(&Device, &ShaderModule) -> RenderPipeline,
+) -> Result<Compiled, String> {
+    let passes = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("passes") => {
+            let text =
+                std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let directory = path.parent().unwrap_or_else(|| Path::new("."));
+            Passes::parse(&text, directory).map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        _ => Passes::single(path),
+    };
+    let image = compile_file(device, &passes.image.file, build_image)?;
+    let buffers = passes
+        .buffers
+        .iter()
+        .map(|pass| {
+            pass.as_ref()
+                .map(|pass| compile_file(device, &pass.file, build_buffer))
+                .transpose()
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Compiled {
+        passes,
+        image,
+        buffers,
+    })
+}
+
+/// A pipeline drawing a full-screen triangle with `fragment` into `target`,
+/// with a depth attachment of `depth_format` when there is one.
+fn create_pipeline(
+    device: &Device,
+    layout: &PipelineLayout,
+    vertex: &ShaderModule,
+    fragment: &ShaderModule,
+    target: wgpu::ColorTargetState,
+    depth_format: Option<TextureFormat>,
+    sample_count: u32,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadertoy Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: vertex,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: fragment,
+            // What GLSL calls it, the WGSL wrapper follows
+            entry_point: "main",
+            targets: &[Some(target)],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        // Covers whatever was drawn before it
+        depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..wgpu::MultisampleState::default()
+        },
+        multiview: None,
+    })
+}
+
+impl Shadertoy {
+    /// Textures for a buffer, cleared to black.
+    fn buffer_textures(device: &Device, width: u32, height: u32) -> [RenderTexture; 2] {
+        [
+            RenderTexture::with_format(device, "Shadertoy Buffer", BUFFER_FORMAT, width, height),
+            RenderTexture::with_format(device, "Shadertoy Buffer", BUFFER_FORMAT, width, height),
+        ]
+    }
+
+    /// What a pass reading `channels` binds at group 2, with `pass` its
+    /// [`PassUniform`].
+    fn bind_group(
+        &self,
+        device: &Device,
+        channels: &[Option<usize>; CHANNELS],
+        pass: &wgpu::Buffer,
+    ) -> BindGroup {
+        let views = channels.map(|channel| -> &TextureView {
+            match channel.and_then(|index| self.buffers[index].as_ref()) {
+                Some(buffer) => &buffer.textures[buffer.front].view,
+                None => &self.black.view,
+            }
+        });
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: self.params_buffer.as_entire_binding(),
+        }];
+        entries.extend(
+            views
+                .iter()
+                .enumerate()
+                .map(|(i, view)| wgpu::BindGroupEntry {
+                    binding: 1 + i as u32,
+                    resource: wgpu::BindingResource::TextureView(view),
+                }),
+        );
+        entries.push(wgpu::BindGroupEntry {
+            binding: 1 + CHANNELS as u32,
+            resource: wgpu::BindingResource::Sampler(&self.sampler),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 2 + CHANNELS as u32,
+            resource: pass.as_entire_binding(),
+        });
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            entries: &entries,
+            label: Some("shadertoy_bind_group"),
+        })
+    }
+}
+
+impl Hack for Shadertoy {
+    fn init(context: &HackContext, args: &Args) -> Self {
+        let device = context.device;
+        let params = ShadertoyUniform {
+            resolution: [context.width as f32, context.height as f32, 1.0],
+            time: 0.0,
+            time_delta: 0.0,
+            frame: 0,
+            _padding: [0.0; 2],
+            mouse: [0.0; 4],
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shadertoy Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Where the buffers can't be filtered they're sampled at the nearest
+        // pixel instead
+        let filterable = context.float32_filterable;
+        if !filterable {
+            log::info!(
+                "{:?} can't be filtered here, buffers are read without",
+                BUFFER_FORMAT
+            );
+        }
+
+        // The uniform, a texture per channel, the sampler for them and the
+        // pass uniform
+        let mut entries = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }];
+        entries.extend((0..CHANNELS as u32).map(|i| wgpu::BindGroupLayoutEntry {
+            binding: 1 + i,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable },
+            },
+            count: None,
+        }));
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 1 + CHANNELS as u32,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(if filterable {
+                wgpu::SamplerBindingType::Filtering
+            } else {
+                wgpu::SamplerBindingType::NonFiltering
+            }),
+            count: None,
+        });
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 2 + CHANNELS as u32,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("shadertoy_bind_group_layout"),
+        });
+
+        let empty_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[],
+            label: Some("shadertoy_empty_bind_group_layout"),
+        });
+        let empty_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &empty_layout,
+            entries: &[],
+            label: Some("shadertoy_empty_bind_group"),
+        });
+
+        let image_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                context.frame_bind_group_layout,
+                context.camera_bind_group_layout,
+                &layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        // Buffers render in passes of their own, keeping the shaders' group 2
+        let buffer_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&empty_layout, &empty_layout, &layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex = device.create_shader_module(wgpu::include_wgsl!("fullscreen.wgsl"));
+        let build_image = |device: &Device, fragment: &ShaderModule| {
+            create_pipeline(
+                device,
+                &image_layout,
+                &vertex,
+                fragment,
+                // Alpha is only for buffers, the screen keeps what it was
+                // cleared to
+                wgpu::ColorTargetState {
+                    format: context.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::COLOR,
+                },
+                Some(context.depth_format),
+                context.sample_count,
+            )
+        };
+        let build_buffer = |device: &Device, fragment: &ShaderModule| {
+            create_pipeline(
+                device,
+                &buffer_layout,
+                &vertex,
+                fragment,
+                BUFFER_FORMAT.into(),
+                None,
+                1,
+            )
+        };
+
+        let file = args.text("shader");
+        let loaded = if file.is_empty() {
+            None
+        } else {
+            load(device, Path::new(&file), &build_image, &build_buffer)
+                .map_err(|e| log::error!("Showing the default shader instead:\n{}", e))
+                .ok()
+        };
+        let compiled = loaded.unwrap_or_else(|| {
+            let path = Path::new("default.glsl");
+            let image = compile(device, path, include_str!("default.glsl"), &build_image)
+                .expect("the default shader works");
+            Compiled {
+                passes: Passes::single(path),
+                image,
+                buffers: (0..BUFFERS).map(|_| None).collect(),
+            }
+        });
+
+        let buffers = compiled
+            .buffers
+            .into_iter()
+            .zip(&compiled.passes.buffers)
+            .map(|(pipeline, pass)| {
+                Some(Buffer {
+                    pipeline: pipeline?,
+                    channels: pass.as_ref()?.channels,
+                    textures: Self::buffer_textures(device, context.width, context.height),
+                    front: 0,
+                })
+            })
+            .collect();
+
+        let filter = if filterable {
+            wgpu::FilterMode::Linear
+        } else {
+            wgpu::FilterMode::Nearest
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadertoy Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+        let black = RenderTexture::new(device, "Shadertoy Black", 1, 1);
+        // Replaced before it's used, but there has to be one
+        let image_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &empty_layout,
+            entries: &[],
+            label: None,
+        });
+
+        Self {
+            image: compiled.image,
+            image_channels: compiled.passes.image.channels,
+            image_bind_group,
+            buffers,
+            layout,
+            empty_bind_group,
+            sampler,
+            black,
+            params,
+            params_buffer,
+            image_pass: pass_buffer(device, true),
+            buffer_pass: pass_buffer(device, false),
+            mouse: Mouse::default(),
+        }
+    }
+
+    fn update(&mut self, context: &mut UpdateContext) {
+        self.mouse.update(context.events, self.params.resolution[1]);
+        let clock = context.clock;
+        self.params.time = clock.elapsed() as f32;
+        self.params.time_delta = clock.delta() as f32;
+        self.params.frame = clock.frame() as i32;
+        self.params.mouse = self.mouse.uniform();
+        context
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[self.params]));
+    }
+
+    fn prepare(&mut self, device: &Device, encoder: &mut CommandEncoder) {
+        for index in 0..BUFFERS {
+            let buffer = match &self.buffers[index] {
+                Some(buffer) => buffer,
+                None => continue,
+            };
+            // A buffer reading itself gets its last frame, what's in front
+            // until it's done
+            let bind_group = self.bind_group(device, &buffer.channels, &self.buffer_pass);
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Shadertoy Buffer Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &buffer.textures[1 - buffer.front].view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                rpass.set_pipeline(&buffer.pipeline);
+                rpass.set_bind_group(0, &self.empty_bind_group, &[]);
+                rpass.set_bind_group(1, &self.empty_bind_group, &[]);
+                rpass.set_bind_group(2, &bind_group, &[]);
+                rpass.draw(0..3, 0..1);
+            }
+            if let Some(buffer) = &mut self.buffers[index] {
+                buffer.front = 1 - buffer.front;
+            }
+        }
+        self.image_bind_group = self.bind_group(device, &self.image_channels, &self.image_pass);
+    }
+
+    fn render<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+        rpass.set_pipeline(&self.image);
+        rpass.set_bind_group(2, &self.image_bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+
+    fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.params.resolution = [width as f32, height as f32, 1.0];
+        // What the buffers had doesn't fit anymore, they start over
+        for buffer in self.buffers.iter_mut().flatten() {
+            buffer.textures = Self::buffer_textures(device, width, height);
+            buffer.front = 0;
+        }
+    }
+
+    fn wants_sky(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+
+/// Number of offscreen buffers, A to D.
+pub const BUFFERS: usize = 4;
+/// Number of `iChannel`s a pass can read.
+pub const CHANNELS: usize = 4;
+
+/// A shader and what it reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pass {
+    pub file: PathBuf,
+    /// The buffer each `iChannel` reads, if any.
+    pub channels: [Option<usize>; CHANNELS],
+}
+
+/// The passes of a multipass shader.
+///
+/// Described in a small text format, one pass per line and `#` starting a
+/// comment. Each line names the pass, its shader relative to the manifest
+/// and the buffers its `iChannel0..3` read, `-` for none:
+///
+/// ```text
+/// buffer a simulate.glsl a   # buffer A reads its own last frame
+/// buffer b blur.glsl a - b   # iChannel1 is left empty
+/// image display.glsl a b     # what ends up on screen
+/// ```
+///
+/// Buffers run in order from A to D, then the image. A buffer that has
+/// already run this frame is read as it is now, any other as it was last
+/// frame, so reading itself gives a pass its own last frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Passes {
+    pub buffers: [Option<Pass>; BUFFERS],
+    pub image: Pass,
+}
+
+impl Passes {
+    /// Just `file` on screen, reading nothing.
+    pub fn single(file: &Path) -> Self {
+        Self {
+            buffers: Default::default(),
+            image: Pass {
+                file: file.to_path_buf(),
+                channels: [None; CHANNELS],
+            },
+        }
+    }
+
+    /// Parse the manifest `text`, with shader files relative to `directory`.
+    pub fn parse(text: &str, directory: &Path) -> Result<Self, String> {
+        let mut buffers: [Option<Pass>; BUFFERS] = Default::default();
+        let mut image = None;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let directive = match words.next() {
+                Some(directive) => directive,
+                None => continue,
+            };
+            let error = |message: String| format!("line {}: {}", number + 1, message);
+            let slot = match directive {
+                "buffer" => {
+                    let name = words
+                        .next()
+                        .ok_or_else(|| error("buffer needs a name".to_string()))?;
+                    let index = buffer(name).map_err(error)?;
+                    &mut buffers[index]
+                }
+                "image" => &mut image,
+                _ => return Err(error(format!("unknown directive: {}", directive))),
+            };
+            if slot.is_some() {
+                return Err(error(format!("{} given twice", directive)));
+            }
+            let file = words
+                .next()
+                .ok_or_else(|| error(format!("{} needs a shader", directive)))?;
+            let mut channels = [None; CHANNELS];
+            for (i, name) in words.enumerate() {
+                if i >= CHANNELS {
+                    return Err(error(format!("more than {} channels", CHANNELS)));
+                }
+                if name != "-" {
+                    channels[i] = Some(buffer(name).map_err(error)?);
+                }
+            }
+            *slot = Some(Pass {
+                file: directory.join(file),
+                channels,
+            });
+        }
+
+        let image = image.ok_or_else(|| "no image pass".to_string())?;
+        for pass in buffers.iter().flatten().chain([&image]) {
+            for channel in pass.channels.iter().flatten() {
+                if buffers[*channel].is_none() {
+                    return Err(format!(
+                        "{} reads buffer {}, which isn't there",
+                        pass.file.display(),
+                        buffer_name(*channel)
+                    ));
+                }
+            }
+        }
+        Ok(Self { buffers, image })
+    }
+}
+
+/// Index of the buffer called `name`, `a` to `d`.
+fn buffer(name: &str) -> Result<usize, String> {
+    match name {
+        "a" | "A" => Ok(0),
+        "b" | "B" => Ok(1),
+        "c" | "C" => Ok(2),
+        "d" | "D" => Ok(3),
+        _ => Err(format!("no buffer {}, they go from a to d", name)),
+    }
+}
+
+/// What buffer `index` is called, `A` to `D`.
+pub fn buffer_name(index: usize) -> char {
+    (b'A' + index as u8) as char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Passes, String> {
+        Passes::parse(text, Path::new("toy"))
+    }
+
+    #[test]
+    fn reads_buffers_and_channels() {
+        let passes = parse(
+            "# comment\n\
+             buffer a sim.glsl a b\n\
+             buffer B blur.glsl - a  # trailing\n\
+             image show.glsl b\n",
+        )
+        .expect("valid");
+        assert_eq!(
+            passes.buffers[0],
+            Some(Pass {
+                file: PathBuf::from("toy/sim.glsl"),
+                channels: [Some(0), Some(1), None, None],
+            })
+        );
+        assert_eq!(
+            passes.buffers[1].as_ref().map(|pass| pass.channels),
+            Some([None, Some(0), None, None])
+        );
+        assert!(passes.buffers[2].is_none() && passes.buffers[3].is_none());
+        assert_eq!(passes.image.file, PathBuf::from("toy/show.glsl"));
+        assert_eq!(passes.image.channels, [Some(1), None, None, None]);
+    }
+
+    #[test]
+    fn needs_an_image() {
+        assert!(parse("buffer a sim.glsl a\n").is_err());
+    }
+
+    #[test]
+    fn channels_must_be_buffers_that_are_there() {
+        let error = parse("image show.glsl a\n").unwrap_err();
+        assert!(error.contains("buffer A"), "{}", error);
+        let error = parse("buffer a sim.glsl e\nimage show.glsl a\n").unwrap_err();
+        assert!(error.starts_with("line 1:"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_passes_given_twice() {
+        let error = parse("image a.glsl\nimage b.glsl\n").unwrap_err();
+        assert!(error.starts_with("line 2:"), "{}", error);
+    }
+}
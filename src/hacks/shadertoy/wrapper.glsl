@@ -0,0 +1,40 @@
+#version 450
+
+// The inputs Shadertoy has that we have too
+layout(set = 2, binding = 0) uniform Shadertoy {
+    vec3 iResolution;
+    float iTime;
+    float iTimeDelta;
+    int iFrame;
+    vec4 iMouse;
+};
+
+// What the buffers rendered, as Shadertoy's iChannels
+layout(set = 2, binding = 1) uniform texture2D xscreensaver_channel0;
+layout(set = 2, binding = 2) uniform texture2D xscreensaver_channel1;
+layout(set = 2, binding = 3) uniform texture2D xscreensaver_channel2;
+layout(set = 2, binding = 4) uniform texture2D xscreensaver_channel3;
+layout(set = 2, binding = 5) uniform sampler xscreensaver_sampler;
+layout(set = 2, binding = 6) uniform XscreensaverPass {
+    uint xscreensaver_flip;
+};
+#define iChannel0 sampler2D(xscreensaver_channel0, xscreensaver_sampler)
+#define iChannel1 sampler2D(xscreensaver_channel1, xscreensaver_sampler)
+#define iChannel2 sampler2D(xscreensaver_channel2, xscreensaver_sampler)
+#define iChannel3 sampler2D(xscreensaver_channel3, xscreensaver_sampler)
+
+layout(location = 0) out vec4 xscreensaver_color;
+
+// mainImage
+
+void main() {
+    // Shadertoy has the origin in the bottom left, buffers are kept upside
+    // down so textures read them with it there too
+    vec2 fragCoord = gl_FragCoord.xy;
+    if (xscreensaver_flip != 0u) {
+        fragCoord.y = iResolution.y - fragCoord.y;
+    }
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(color, fragCoord);
+    xscreensaver_color = color;
+}
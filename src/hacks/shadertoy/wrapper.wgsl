@@ -0,0 +1,41 @@
+// The inputs Shadertoy has that we have too
+struct Shadertoy {
+    iResolution: vec3<f32>,
+    iTime: f32,
+    iTimeDelta: f32,
+    iFrame: i32,
+    iMouse: vec4<f32>,
+};
+@group(2) @binding(0)
+var<uniform> shadertoy: Shadertoy;
+
+// What the buffers rendered, as Shadertoy's iChannels
+@group(2) @binding(1)
+var iChannel0: texture_2d<f32>;
+@group(2) @binding(2)
+var iChannel1: texture_2d<f32>;
+@group(2) @binding(3)
+var iChannel2: texture_2d<f32>;
+@group(2) @binding(4)
+var iChannel3: texture_2d<f32>;
+@group(2) @binding(5)
+var channel_sampler: sampler;
+
+struct XscreensaverPass {
+    flip: u32,
+};
+@group(2) @binding(6)
+var<uniform> xscreensaver_pass: XscreensaverPass;
+
+// mainImage
+
+@fragment
+fn main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // Shadertoy has the origin in the bottom left, buffers are kept upside
+    // down so textures read them with it there too
+    var fragCoord = position.xy;
+    if (xscreensaver_pass.flip != 0u) {
+        fragCoord.y = shadertoy.iResolution.y - fragCoord.y;
+    }
+    return mainImage(fragCoord);
+}
@@ -0,0 +1,150 @@
+//! Finding the images and decoding them, away from the frames.
+
+use image::RgbaImage;
+use rand::seq::SliceRandom;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+
+use crate::texture;
+
+/// Images decoded ahead of being shown, besides the one being decoded.
+const AHEAD: usize = 1;
+
+/// Extensions of the files we show, in lower case.
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+/// An image ready to go on the GPU, the right way up.
+pub struct Loaded {
+    pub path: PathBuf,
+    /// See [`texture::mipmaps`].
+    pub mipmaps: Vec<RgbaImage>,
+}
+
+/// Where to look for images and how to show them.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub directory: PathBuf,
+    /// Look in subdirectories too.
+    pub recursive: bool,
+    /// Random order instead of by path, reshuffled every time around.
+    pub shuffle: bool,
+    /// Longest side images are scaled down to.
+    pub max_size: u32,
+}
+
+/// Decodes the images, and makes their mipmaps, one after the other, over
+/// and over, on tokio's blocking threads.
+pub struct Loader {
+    receiver: Receiver<Loaded>,
+}
+
+impl Loader {
+    /// Start scanning for images and decoding them. Has to be called from
+    /// within the tokio runtime.
+    pub fn spawn(options: Options) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(AHEAD);
+        // Blocks whenever it's far enough ahead, and stops when we're dropped
+        tokio::task::spawn_blocking(move || run(&options, &sender));
+        Self { receiver }
+    }
+
+    /// The next image, if it's decoded already.
+    pub fn try_next(&self) -> Option<Loaded> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// The next image, waiting for it to be decoded. `None` when there
+    /// aren't any.
+    pub fn next(&self) -> Option<Loaded> {
+        self.receiver.recv().ok()
+    }
+}
+
+fn run(options: &Options, sender: &SyncSender<Loaded>) {
+    let directory = &options.directory;
+    let mut paths = scan(directory, options.recursive);
+    if paths.is_empty() {
+        log::error!("No images in {}", directory.display());
+        return;
+    }
+    log::info!(
+        "Showing {} images from {}",
+        paths.len(),
+        directory.display()
+    );
+    loop {
+        if options.shuffle {
+            paths.shuffle(&mut rand::thread_rng());
+        }
+        let mut any = false;
+        for path in &paths {
+            match texture::decode(path, options.max_size) {
+                Ok(image) => {
+                    let loaded = Loaded {
+                        path: path.clone(),
+                        mipmaps: texture::mipmaps(&image),
+                    };
+                    // The slideshow is gone
+                    if sender.send(loaded).is_err() {
+                        return;
+                    }
+                    any = true;
+                }
+                Err(e) => log::warn!("Skipping {}", e),
+            }
+        }
+        if !any {
+            log::error!("None of the images in {} can be read", directory.display());
+            return;
+        }
+    }
+}
+
+/// Paths of the images in `directory`, sorted.
+fn scan(directory: &Path, recursive: bool) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let entries = match std::fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Can't look in {}: {}", directory.display(), e);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // Not following links to directories, they could go round in
+            // circles
+            let is_directory = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            if is_directory {
+                if recursive {
+                    directories.push(path);
+                }
+            } else if is_image(&path) && path.is_file() {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_known_extensions_are_images() {
+        assert!(is_image(Path::new("a/photo.JPG")));
+        assert!(is_image(Path::new("logo.png")));
+        assert!(!is_image(Path::new("notes.txt")));
+        assert!(!is_image(Path::new("jpeg")));
+    }
+}
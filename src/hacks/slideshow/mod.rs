@@ -0,0 +1,568 @@
+//! A slideshow of the photos in a directory, slowly panning and zooming over
+//! each one, with a transition from one to the next.
+//!
+//! Images are decoded in the background, see [`loader`], and letterboxed to
+//! fit the screen whichever way round they are.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::PathBuf;
+use std::rc::Rc;
+use wgpu::{BindGroup, BindGroupLayout, Device, Queue, RenderPass};
+
+use crate::args::Args;
+use crate::hacks::{Hack, HackContext, UpdateContext};
+use crate::params::{Kind, Parameter};
+use crate::primitives::TexturedVertex;
+use crate::shader::{wgsl, ReloadablePipeline};
+use crate::texture::{self, Texture};
+
+mod loader;
+
+use loader::{Loaded, Loader, Options};
+
+/// Longest side images are scaled down to, plenty for a screen even when
+/// zoomed in a bit.
+const MAX_SIZE: u32 = 4096;
+
+/// Quads drawn for a slide: a bar on either side of the image, and the image.
+const QUADS: usize = 3;
+
+/// Slides on screen at once, the one going and the one coming during a
+/// transition.
+const SLIDES: usize = 2;
+
+pub const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        id: "imagedirectory",
+        label: "Image directory (slideshow only)",
+        arg: "-image-directory",
+        kind: Kind::File,
+    },
+    Parameter {
+        id: "recursive",
+        label: "Include subdirectories (slideshow only)",
+        arg: "-recursive",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "shuffle",
+        label: "Random order (slideshow only)",
+        arg: "-shuffle",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "duration",
+        label: "Time per image (slideshow only)",
+        arg: "-duration",
+        kind: Kind::Slider {
+            low: 1.0,
+            high: 60.0,
+            default: 10.0,
+            low_label: "1 second",
+            high_label: "1 minute",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "transition",
+        label: "Transition (slideshow only)",
+        arg: "-transition",
+        kind: Kind::Select {
+            options: &[
+                ("crossfade", "Crossfade"),
+                ("slide", "Slide"),
+                ("dissolve", "Dissolve"),
+            ],
+            default: "crossfade",
+        },
+    },
+    Parameter {
+        id: "transitiontime",
+        label: "Transition time (slideshow only)",
+        arg: "-transition-time",
+        kind: Kind::Slider {
+            low: 0.1,
+            high: 5.0,
+            default: 1.5,
+            low_label: "Quick",
+            high_label: "Slow",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "panzoom",
+        label: "Pan and zoom (slideshow only)",
+        arg: "-pan-zoom",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 0.5,
+            default: 0.15,
+            low_label: "Still",
+            high_label: "Lots",
+            invert: false,
+        },
+    },
+];
+
+/// How one slide makes way for the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transition {
+    /// The next one fades in over it.
+    Crossfade,
+    /// The next one pushes it off to the left.
+    Slide,
+    /// The next one shows up a few pixels at a time.
+    Dissolve,
+}
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SlideUniform {
+    opacity: f32,
+    /// How much of the slide shows through the dissolve, from 0 to 1
+    dissolve: f32,
+    // Uniforms are laid out in 16 byte chunks
+    _padding: [f32; 2],
+}
+
+/// The part of an image in view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Framing {
+    /// 1 for the whole image, 2 for half of it.
+    zoom: f32,
+    /// Where the part in view is, from 0,0 at the top left of the image to
+    /// 1,1 at the bottom right. Only matters zoomed in.
+    center: [f32; 2],
+}
+
+impl Framing {
+    /// Texture coordinates of the top left corner of what's in view, and
+    /// its size.
+    fn crop(&self) -> ([f32; 2], f32) {
+        let size = 1.0 / self.zoom;
+        let origin = self.center.map(|center| center * (1.0 - size));
+        (origin, size)
+    }
+
+    fn lerp(&self, other: &Framing, t: f32) -> Framing {
+        Framing {
+            zoom: self.zoom + (other.zoom - self.zoom) * t,
+            center: [0, 1].map(|i| self.center[i] + (other.center[i] - self.center[i]) * t),
+        }
+    }
+}
+
+/// An image on its way through the slideshow.
+struct Slide {
+    texture: Texture,
+    bind_group: BindGroup,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: BindGroup,
+    /// Clock time it started coming in.
+    shown: f64,
+    /// Where the pan and zoom starts and ends.
+    from: Framing,
+    to: Framing,
+}
+
+/// Shows the images in a directory one after the other.
+pub struct Slideshow {
+    pipeline: ReloadablePipeline,
+    /// Draws the bars around images that don't fit the screen.
+    bars: ReloadablePipeline,
+    texture_layout: BindGroupLayout,
+    params_layout: BindGroupLayout,
+    vertex_buffer: wgpu::Buffer,
+    loader: Loader,
+    current: Option<Slide>,
+    next: Option<Slide>,
+    /// Slides made so far, seeds how each one moves.
+    count: u64,
+    /// Width over height of the screen.
+    aspect: f32,
+    duration: f64,
+    transition: Transition,
+    transition_time: f64,
+    pan_zoom: f32,
+}
+
+/// Half the width and height, in clip space, of an image with `aspect` as
+/// large as it fits on a screen with `screen_aspect`.
+fn fit(aspect: f32, screen_aspect: f32) -> [f32; 2] {
+    if aspect > screen_aspect {
+        [1.0, screen_aspect / aspect]
+    } else {
+        [aspect / screen_aspect, 1.0]
+    }
+}
+
+/// A quad from `min` to `max` in clip space, as a triangle strip, showing
+/// the `crop` of a texture.
+fn rect(min: [f32; 2], max: [f32; 2], crop: ([f32; 2], f32)) -> [TexturedVertex; 4] {
+    let ([u, v], size) = crop;
+    [
+        ([min[0], max[1]], [u, v]),
+        ([min[0], min[1]], [u, v + size]),
+        ([max[0], max[1]], [u + size, v]),
+        ([max[0], min[1]], [u + size, v + size]),
+    ]
+    .map(|([x, y], uv)| TexturedVertex {
+        position: [x, y, 0.0],
+        uv,
+    })
+}
+
+/// The [`QUADS`] of a slide `x` across the screen, with an image of half
+/// `size` in the middle showing `framing`.
+fn quads(x: f32, size: [f32; 2], framing: &Framing) -> [[TexturedVertex; 4]; QUADS] {
+    let [width, height] = size;
+    let none = ([0.0; 2], 0.0);
+    // One of them is empty when the image fits the screen
+    let (before, after) = if width < 1.0 {
+        (
+            rect([x - 1.0, -1.0], [x - width, 1.0], none),
+            rect([x + width, -1.0], [x + 1.0, 1.0], none),
+        )
+    } else {
+        (
+            rect([x - 1.0, -1.0], [x + 1.0, -height], none),
+            rect([x - 1.0, height], [x + 1.0, 1.0], none),
+        )
+    };
+    let image = rect([x - width, -height], [x + width, height], framing.crop());
+    [before, after, image]
+}
+
+fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl Slideshow {
+    /// A [`Slide`] for a newly loaded image, coming in at `now`.
+    fn slide(&mut self, device: &Device, queue: &Queue, loaded: Loaded, now: f64) -> Slide {
+        log::debug!("Showing {}", loaded.path.display());
+        let texture = Texture::from_mipmaps(
+            device,
+            queue,
+            &loaded.mipmaps,
+            &loaded.path.to_string_lossy(),
+        );
+        let bind_group = texture.bind_group(device, &self.texture_layout);
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Slide Params Buffer"),
+            size: std::mem::size_of::<SlideUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.params_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("slide_params_bind_group"),
+        });
+
+        // Zooming in or out, towards somewhere else
+        let mut rng = StdRng::seed_from_u64(self.count);
+        self.count += 1;
+        let mut framing = |zoom| Framing {
+            zoom,
+            center: [rng.gen(), rng.gen()],
+        };
+        let (mut from, mut to) = (framing(1.0), framing(1.0 + self.pan_zoom));
+        if rng.gen() {
+            std::mem::swap(&mut from, &mut to);
+        }
+
+        Slide {
+            texture,
+            bind_group,
+            params_buffer,
+            params_bind_group,
+            shown: now,
+            from,
+            to,
+        }
+    }
+
+    /// How far along the transition to `next` is, from 0 to 1.
+    fn progress(&self, next: &Slide, now: f64) -> f32 {
+        ((now - next.shown) / self.transition_time).clamp(0.0, 1.0) as f32
+    }
+
+    /// Move the slides to where they are at `now`.
+    fn place(&self, queue: &Queue, now: f64) {
+        let mut vertices = [[TexturedVertex {
+            position: [0.0; 3],
+            uv: [0.0; 2],
+        }; 4]; QUADS * SLIDES];
+
+        let progress = self
+            .next
+            .as_ref()
+            .map_or(0.0, |next| self.progress(next, now));
+        // Where across the screen the slides going and coming are
+        let (going, coming) = match self.transition {
+            Transition::Slide => {
+                let pushed = smoothstep(progress) * 2.0;
+                (-pushed, 2.0 - pushed)
+            }
+            _ => (0.0, 0.0),
+        };
+        // Coming in and going out, the transition is part of the time on
+        // screen
+        let visible = self.duration + 2.0 * self.transition_time;
+        let slides = [(&self.current, going), (&self.next, coming)];
+        for (i, (slide, x)) in slides.into_iter().enumerate() {
+            let slide = match slide {
+                Some(slide) => slide,
+                None => continue,
+            };
+            let t = ((now - slide.shown) / visible).clamp(0.0, 1.0) as f32;
+            let framing = slide.from.lerp(&slide.to, t);
+            let size = fit(slide.texture.aspect(), self.aspect);
+            vertices[QUADS * i..QUADS * (i + 1)].copy_from_slice(&quads(x, size, &framing));
+
+            let is_next = i == 1;
+            let params = SlideUniform {
+                opacity: match self.transition {
+                    Transition::Crossfade if is_next => progress,
+                    _ => 1.0,
+                },
+                dissolve: match self.transition {
+                    Transition::Dissolve if is_next => progress,
+                    _ => 1.0,
+                },
+                _padding: [0.0; 2],
+            };
+            queue.write_buffer(&slide.params_buffer, 0, bytemuck::cast_slice(&[params]));
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+    }
+}
+
+impl Hack for Slideshow {
+    fn init(context: &HackContext, args: &Args) -> Self {
+        let device = context.device;
+        let texture_layout = texture::bind_group_layout(device);
+        let params_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("slide_params_bind_group_layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                context.frame_bind_group_layout,
+                context.camera_bind_group_layout,
+                &texture_layout,
+                &params_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let (format, depth_format, sample_count) =
+            (context.format, context.depth_format, context.sample_count);
+        let build = move |device: &Device,
+                          shader: &wgpu::ShaderModule,
+                          layout: &wgpu::PipelineLayout,
+                          entry_point: &str| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Slideshow Pipeline"),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vs_main",
+                    buffers: &[TexturedVertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..wgpu::PrimitiveState::default()
+                },
+                // Covers whatever was drawn before it, in the order drawn
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: depth_format,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..wgpu::MultisampleState::default()
+                },
+                multiview: None,
+            })
+        };
+        // Both pipelines keep it around to be rebuilt with
+        let pipeline_layout = Rc::new(pipeline_layout);
+        let layout = pipeline_layout.clone();
+        let pipeline = ReloadablePipeline::new(
+            device,
+            wgsl!("slideshow.wgsl"),
+            context.reload_shaders,
+            move |device, shader| build(device, shader, &layout, "fs_main"),
+        );
+        let bars = ReloadablePipeline::new(
+            device,
+            wgsl!("slideshow.wgsl"),
+            context.reload_shaders,
+            move |device, shader| build(device, shader, &pipeline_layout, "fs_bars"),
+        );
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Slideshow Vertex Buffer"),
+            size: (4 * QUADS * SLIDES * std::mem::size_of::<TexturedVertex>())
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let directory = match args.text("imagedirectory") {
+            directory if directory.is_empty() => std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join("Pictures"))
+                .unwrap_or_default(),
+            directory => PathBuf::from(directory),
+        };
+        let loader = Loader::spawn(Options {
+            directory,
+            recursive: args.flag("recursive"),
+            shuffle: args.flag("shuffle"),
+            max_size: MAX_SIZE.min(device.limits().max_texture_dimension_2d),
+        });
+
+        Self {
+            pipeline,
+            bars,
+            texture_layout,
+            params_layout,
+            vertex_buffer,
+            loader,
+            current: None,
+            next: None,
+            count: 0,
+            aspect: context.width as f32 / context.height as f32,
+            duration: args.number("duration"),
+            transition: match args.text("transition").as_str() {
+                "slide" => Transition::Slide,
+                "dissolve" => Transition::Dissolve,
+                _ => Transition::Crossfade,
+            },
+            transition_time: args.number("transitiontime"),
+            pan_zoom: args.number("panzoom") as f32,
+        }
+    }
+
+    fn update(&mut self, context: &mut UpdateContext) {
+        self.pipeline.reload(context.device);
+        self.bars.reload(context.device);
+        let now = context.clock.elapsed();
+
+        let due = match &self.current {
+            None => true,
+            Some(current) => {
+                self.next.is_none() && now - current.shown >= self.transition_time + self.duration
+            }
+        };
+        if due {
+            // With all the time in the world we wait for the next image, on
+            // screen the last one stays up until it's ready
+            let loaded = if context.clock.is_fixed() {
+                self.loader.next()
+            } else {
+                self.loader.try_next()
+            };
+            if let Some(loaded) = loaded {
+                let slide = self.slide(context.device, context.queue, loaded, now);
+                match self.current {
+                    None => self.current = Some(slide),
+                    Some(_) => self.next = Some(slide),
+                }
+            }
+        }
+        if let Some(next) = &self.next {
+            if self.progress(next, now) >= 1.0 {
+                self.current = self.next.take();
+            }
+        }
+        self.place(context.queue, now);
+    }
+
+    fn render<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        for (i, slide) in [&self.current, &self.next].into_iter().enumerate() {
+            if let Some(slide) = slide {
+                let start = (4 * QUADS * i) as u32;
+                rpass.set_bind_group(2, &slide.bind_group, &[]);
+                rpass.set_bind_group(3, &slide.params_bind_group, &[]);
+                rpass.set_pipeline(self.bars.pipeline());
+                rpass.draw(start..start + 4, 0..1);
+                rpass.draw(start + 4..start + 8, 0..1);
+                rpass.set_pipeline(self.pipeline.pipeline());
+                rpass.draw(start + 8..start + 12, 0..1);
+            }
+        }
+    }
+
+    fn resize(&mut self, _device: &Device, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+
+    // The bars around images that don't fit the screen stay black
+    fn wants_sky(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_images_get_bars_above_and_below() {
+        assert_eq!(fit(2.0, 1.0), [1.0, 0.5]);
+        assert_eq!(fit(16.0 / 9.0, 16.0 / 9.0), [1.0, 1.0]);
+    }
+
+    #[test]
+    fn tall_images_get_bars_at_the_sides() {
+        assert_eq!(fit(0.5, 2.0), [0.25, 1.0]);
+    }
+
+    #[test]
+    fn crops_stay_inside_the_image() {
+        for center in [[0.0, 0.0], [1.0, 1.0], [0.3, 0.9]] {
+            let (origin, size) = Framing { zoom: 1.5, center }.crop();
+            for o in origin {
+                assert!(o >= 0.0 && o + size <= 1.0, "{:?} {}", origin, size);
+            }
+        }
+        let whole = Framing {
+            zoom: 1.0,
+            center: [0.7, 0.2],
+        };
+        assert_eq!(whole.crop(), ([0.0, 0.0], 1.0));
+    }
+}
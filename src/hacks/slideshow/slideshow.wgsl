@@ -0,0 +1,65 @@
+@group(2) @binding(0)
+var photo: texture_2d<f32>;
+@group(2) @binding(1)
+var photo_sampler: sampler;
+
+struct SlideUniform {
+    opacity: f32,
+    // Pixels with noise above this aren't drawn yet
+    dissolve: f32,
+};
+@group(3) @binding(0)
+var<uniform> slide: SlideUniform;
+
+// Side of the squares the dissolve reveals, in pixels
+let DISSOLVE_CELL: f32 = 3.0;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Vertex Shader
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    // Already on the screen, no camera involved
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(in.position, 1.0);
+    out.uv = in.uv;
+    return out;
+}
+
+// Noise from 0 up to 1, fixed for each cell of the screen
+fn noise(position: vec2<f32>) -> f32 {
+    let cell = vec2<u32>(position / DISSOLVE_CELL);
+    var h = cell.x * 0x27d4eb2du ^ cell.y * 0x165667b1u;
+    h = (h ^ (h >> 15u)) * 0x2c1b3c6du;
+    h = h ^ (h >> 12u);
+    return f32(h & 0xffffu) / 65536.0;
+}
+
+// Fragment Shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sampled before discarding, it needs every pixel around to be there
+    let color = textureSample(photo, photo_sampler, in.uv);
+    if (noise(in.clip_position.xy) >= slide.dissolve) {
+        discard;
+    }
+    return vec4<f32>(color.rgb, color.a * slide.opacity);
+}
+
+// The bars around images that don't fit the screen, coming and going with
+// them
+@fragment
+fn fs_bars(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (noise(in.clip_position.xy) >= slide.dissolve) {
+        discard;
+    }
+    return vec4<f32>(0.0, 0.0, 0.0, slide.opacity);
+}
@@ -1,23 +1,161 @@
-mod ground;
+mod args;
+mod camera_path;
+mod depth;
+mod hacks;
+mod msaa;
+mod offscreen;
+mod params;
+mod post;
 mod primitives;
+mod shader;
+mod shutdown;
+mod sky;
 mod state;
+mod surface;
+mod texture;
 mod xscreensaver;
 
+use args::Args;
+use shutdown::Shutdown;
 use xscreensaver::ScreensaverWindow;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
     env_logger::init();
-    if let Ok(window) = ScreensaverWindow::new() {
-        let mut setup = state::State::setup(&window, 30).await;
-        loop {
-            for event in window.process_events() {
-                match event {
+    let args = match Args::from_env() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, args::usage());
+            std::process::exit(1);
+        }
+    };
+    if args.help {
+        print!("{}", args::usage());
+        return;
+    }
+    if args.print_xml {
+        print!(
+            "{}",
+            params::xscreensaver_xml(
+                "xscreensaver_wgpu",
+                "XScreensaver WGPU",
+                "Neon lines sliding towards you across a dark plane, rendered with wgpu.",
+                args::parameters(),
+            )
+        );
+        return;
+    }
+    let mut shutdown = Shutdown::listen().expect("Failed to install signal handlers");
+    if args.offscreen {
+        run_offscreen(&args, &mut shutdown).await;
+    } else {
+        run_window(&args, &mut shutdown).await;
+    }
+}
+
+async fn run_window(args: &Args, shutdown: &mut Shutdown) {
+    if let Ok(window) = ScreensaverWindow::new(args.target, args.geometry) {
+        // Only while developing in a window of our own, not in xscreensaver's
+        let reload_shaders = args.reload_shaders && window.owns_window();
+        let mut setup = state::State::setup(&window, reload_shaders, args).await;
+        let mut visible = true;
+        'main: loop {
+            let mut redraw = false;
+            let events = window.process_events();
+            for event in &events {
+                match *event {
                     xscreensaver::Event::Resized { width, height } => setup.resize(width, height),
+                    xscreensaver::Event::KeyPressed { keycode, keysym } => {
+                        log::debug!("Key pressed: keycode {} keysym {:#x}", keycode, keysym)
+                    }
+                    // The hack gets these with the rest in update
+                    xscreensaver::Event::PointerMoved { .. }
+                    | xscreensaver::Event::ButtonPressed { .. }
+                    | xscreensaver::Event::ButtonReleased { .. } => {}
+                    xscreensaver::Event::Exposed => redraw = true,
+                    xscreensaver::Event::VisibilityChanged { obscured } => visible = !obscured,
+                    xscreensaver::Event::Destroyed => {
+                        log::info!("Window destroyed, exiting");
+                        break 'main;
+                    }
+                }
+            }
+            setup.update(&events);
+            // No point burning GPU on a window nobody can see
+            if visible || redraw {
+                if let Err(e) = setup.render(Some(&window)) {
+                    log::error!("Stopping, can't render: {}", e);
+                    break;
+                }
+            }
+            tokio::select! {
+                _ = setup.tick() => {}
+                signal = shutdown.recv() => {
+                    log::info!("Received {}, exiting", signal);
+                    break;
+                }
+            }
+        }
+        // Let the last frame finish before tearing down, the window closes
+        // its display when dropped
+        setup.finish();
+        drop(setup);
+        drop(window);
+    } else {
+        log::error!("Failed to open the X display");
+        std::process::exit(1);
+    }
+}
+
+/// Render without a window, either until told to stop or, with
+/// `-render-frames`, writing a fixed number of frames to PNGs.
+async fn run_offscreen(args: &Args, shutdown: &mut Shutdown) {
+    if let Some(frames) = args.render_frames {
+        if let Err(e) = std::fs::create_dir_all(&args.output) {
+            log::error!("Can't create {}: {}", args.output.display(), e);
+            std::process::exit(1);
+        }
+        log::info!("Rendering {} frames to {}", frames, args.output.display());
+    }
+    let mut setup =
+        state::State::setup_offscreen(args.geometry.width, args.geometry.height, args).await;
+    let mut frame = 0;
+    loop {
+        if args.render_frames.is_some_and(|frames| frame >= frames) {
+            break;
+        }
+        setup.update(&[]);
+        if let Err(e) = setup.render(None) {
+            log::error!("Stopping, can't render: {}", e);
+            break;
+        }
+        if args.render_frames.is_some() {
+            let path = args.output.join(format!("frame_{:05}.png", frame));
+            let image = setup.capture().expect("rendering offscreen");
+            if let Err(e) = image.save(&path) {
+                log::error!("Can't write {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+            // Frames are written as fast as we can render them, but still
+            // give the signal handlers a chance to run
+            setup.advance();
+            tokio::select! {
+                _ = tokio::task::yield_now() => {}
+                signal = shutdown.recv() => {
+                    log::info!("Received {}, exiting", signal);
+                    break;
+                }
+            }
+        } else {
+            tokio::select! {
+                _ = setup.tick() => {}
+                signal = shutdown.recv() => {
+                    log::info!("Received {}, exiting", signal);
+                    break;
                 }
             }
-            setup.render();
-            setup.tick();
         }
+        frame += 1;
     }
+    setup.finish();
 }
@@ -0,0 +1,72 @@
+use wgpu::{Adapter, Device, TextureFormat, TextureFormatFeatureFlags, TextureView};
+
+use crate::depth::DEPTH_FORMAT;
+use crate::post::{HDR_FORMAT, MSAA_FALLBACK_FORMAT};
+
+/// Multisampled color target the main pass renders into, resolved into the
+/// surface texture at the end of the pass.
+pub struct MultisampleTarget {
+    _texture: wgpu::Texture,
+    pub view: TextureView,
+}
+
+impl MultisampleTarget {
+    pub fn new(
+        device: &Device,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Multisample Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            _texture: texture,
+            view,
+        }
+    }
+}
+
+/// The format to render the scene in and its sample count, when `requested`
+/// samples were asked for, given what `adapter` can do with our depth buffer.
+///
+/// The scene is [`HDR_FORMAT`], unless that can't be multisampled and
+/// [`MSAA_FALLBACK_FORMAT`] can. Render passes in this version of wgpu only
+/// take 1 or 4 samples, those are all `-samples` offers, and 2 or 8 are
+/// taken as 4 when parsing.
+pub fn scene_format(adapter: &Adapter, requested: u32) -> (TextureFormat, u32) {
+    if requested <= 1 {
+        return (HDR_FORMAT, 1);
+    }
+    let flags = |format| adapter.get_texture_format_features(format).flags;
+    let multisample = |format| {
+        flags(format).contains(
+            TextureFormatFeatureFlags::MULTISAMPLE | TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE,
+        ) && flags(DEPTH_FORMAT).contains(TextureFormatFeatureFlags::MULTISAMPLE)
+    };
+    if multisample(HDR_FORMAT) {
+        (HDR_FORMAT, requested)
+    } else if multisample(MSAA_FALLBACK_FORMAT) {
+        log::info!(
+            "{:?} can't be multisampled here, rendering the scene in {:?}",
+            HDR_FORMAT,
+            MSAA_FALLBACK_FORMAT
+        );
+        (MSAA_FALLBACK_FORMAT, requested)
+    } else {
+        log::warn!("Nothing can be multisampled here, MSAA is off");
+        (HDR_FORMAT, 1)
+    }
+}
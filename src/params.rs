@@ -0,0 +1,445 @@
+use std::fmt::Write;
+
+/// A user facing option of the hack. The command line parser and the
+/// xscreensaver-settings XML are both generated from these, so they can't
+/// disagree.
+#[derive(Debug)]
+pub struct Parameter {
+    /// Identifies the parameter in the XML and when looking up its value.
+    pub id: &'static str,
+    pub label: &'static str,
+    /// Command line switch, `-name`.
+    pub arg: &'static str,
+    pub kind: Kind,
+}
+
+#[derive(Debug)]
+pub enum Kind {
+    /// Number picked with a slider. `invert` flips the slider, for values
+    /// like delays where less means more.
+    Slider {
+        low: f64,
+        high: f64,
+        default: f64,
+        low_label: &'static str,
+        high_label: &'static str,
+        invert: bool,
+    },
+    /// Number picked with a spin button.
+    Spin { low: f64, high: f64, default: f64 },
+    /// Off unless the switch is given.
+    Toggle,
+    /// One of a fixed set of `(value, label)` options.
+    Select {
+        options: &'static [(&'static str, &'static str)],
+        default: &'static str,
+    },
+    /// Comma separated names from `options`, in the order given.
+    List {
+        options: &'static [(&'static str, &'static str)],
+        default: &'static str,
+    },
+    /// `#rrggbb`
+    Color { default: &'static str },
+    /// Path to a file, empty for none.
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Flag(bool),
+    Text(String),
+}
+
+impl Parameter {
+    pub fn default_value(&self) -> Value {
+        match self.kind {
+            Kind::Slider { default, .. } | Kind::Spin { default, .. } => Value::Number(default),
+            Kind::Toggle => Value::Flag(false),
+            Kind::Select { default, .. } | Kind::List { default, .. } | Kind::Color { default } => {
+                Value::Text(default.to_string())
+            }
+            Kind::File => Value::Text(String::new()),
+        }
+    }
+
+    /// Whether the parameter is a switch without a value.
+    pub fn is_flag(&self) -> bool {
+        matches!(self.kind, Kind::Toggle)
+    }
+
+    /// Turn the command line value into a [`Value`], checking it's one we
+    /// can use.
+    pub fn parse(&self, value: Option<&str>) -> Result<Value, String> {
+        let value = match (&self.kind, value) {
+            (Kind::Toggle, _) => return Ok(Value::Flag(true)),
+            (_, Some(value)) => value,
+            (_, None) => return Err(format!("{} needs a value", self.arg)),
+        };
+        let invalid = || format!("invalid value for {}: {}", self.arg, value);
+        match self.kind {
+            Kind::Slider { low, high, .. } | Kind::Spin { low, high, .. } => {
+                let number: f64 = value.parse().map_err(|_| invalid())?;
+                if number < low || number > high {
+                    return Err(format!(
+                        "{} must be between {} and {}, got {}",
+                        self.arg, low, high, value
+                    ));
+                }
+                Ok(Value::Number(number))
+            }
+            Kind::Select { options, .. } => options
+                .iter()
+                .find(|(option, _)| *option == value)
+                .map(|_| Value::Text(value.to_string()))
+                .ok_or_else(invalid),
+            Kind::List { options, .. } => {
+                for name in list(value) {
+                    if !options.iter().any(|(option, _)| *option == name) {
+                        return Err(format!("unknown name for {}: {}", self.arg, name));
+                    }
+                }
+                Ok(Value::Text(value.to_string()))
+            }
+            Kind::Color { .. } => parse_color(value)
+                .map(|_| Value::Text(value.to_string()))
+                .ok_or_else(invalid),
+            Kind::File => Ok(Value::Text(value.to_string())),
+            Kind::Toggle => unreachable!(),
+        }
+    }
+}
+
+/// The names in a [`Kind::List`] value.
+pub fn list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// `#rrggbb` to linear-ish `[r, g, b]` in 0..1.
+pub fn parse_color(value: &str) -> Option<[f32; 3]> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(hex.get(i..i + 2)?, 16)
+            .ok()
+            .map(|c| c as f32 / 255.0)
+    };
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The `hacks/config/<name>.xml` describing `parameters` to
+/// xscreensaver-settings.
+pub fn xscreensaver_xml<'p>(
+    name: &str,
+    label: &str,
+    description: &str,
+    parameters: impl IntoIterator<Item = &'p Parameter>,
+) -> String {
+    let mut xml = String::new();
+    // Writing to a String can't fail
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="ISO-8859-1"?>"#);
+    let _ = writeln!(
+        xml,
+        r#"<screensaver name="{}" _label="{}">"#,
+        escape(name),
+        escape(label)
+    );
+    for parameter in parameters {
+        let (id, label, arg) = (
+            escape(parameter.id),
+            escape(parameter.label),
+            escape(parameter.arg),
+        );
+        let _ = match parameter.kind {
+            Kind::Slider {
+                low,
+                high,
+                default,
+                low_label,
+                high_label,
+                invert,
+            } => writeln!(
+                xml,
+                r#"  <number id="{}" type="slider" arg="{} %" _label="{}" _low-label="{}" _high-label="{}" low="{}" high="{}" default="{}"{}/>"#,
+                id,
+                arg,
+                label,
+                escape(low_label),
+                escape(high_label),
+                low,
+                high,
+                default,
+                if invert { r#" convert="invert""# } else { "" }
+            ),
+            Kind::Spin { low, high, default } => writeln!(
+                xml,
+                r#"  <number id="{}" type="spinbutton" arg="{} %" _label="{}" low="{}" high="{}" default="{}"/>"#,
+                id, arg, label, low, high, default
+            ),
+            Kind::Toggle => writeln!(
+                xml,
+                r#"  <boolean id="{}" _label="{}" arg-set="{}"/>"#,
+                id, label, arg
+            ),
+            Kind::Select { options, default } => {
+                let _ = writeln!(xml, r#"  <select id="{}">"#, id);
+                for (value, option_label) in options {
+                    // The default is what we get without passing anything
+                    let arg_set = if *value == default {
+                        String::new()
+                    } else {
+                        format!(r#" arg-set="{} {}""#, arg, escape(value))
+                    };
+                    let _ = writeln!(
+                        xml,
+                        r#"    <option id="{}" _label="{}"{}/>"#,
+                        escape(value),
+                        escape(option_label),
+                        arg_set
+                    );
+                }
+                writeln!(xml, "  </select>")
+            }
+            Kind::List { .. } | Kind::Color { .. } => writeln!(
+                xml,
+                r#"  <string id="{}" _label="{}" arg="{} %"/>"#,
+                id, label, arg
+            ),
+            Kind::File => writeln!(
+                xml,
+                r#"  <file id="{}" _label="{}" arg="{} %"/>"#,
+                id, label, arg
+            ),
+        };
+    }
+    let _ = writeln!(
+        xml,
+        "  <_description>{}</_description>",
+        escape(description)
+    );
+    let _ = writeln!(xml, "</screensaver>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLIDER: Parameter = Parameter {
+        id: "delay",
+        label: "Frame rate",
+        arg: "-delay",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 100.0,
+            default: 50.0,
+            low_label: "Low",
+            high_label: "High",
+            invert: true,
+        },
+    };
+
+    const SELECT: Parameter = Parameter {
+        id: "style",
+        label: "Style",
+        arg: "-style",
+        kind: Kind::Select {
+            options: &[("bars", "Bars"), ("grid", "Grid & more")],
+            default: "bars",
+        },
+    };
+
+    const LIST: Parameter = Parameter {
+        id: "post",
+        label: "Effects",
+        arg: "-post",
+        kind: Kind::List {
+            options: &[("bloom", "Bloom"), ("crt", "CRT")],
+            default: "bloom",
+        },
+    };
+
+    /// The lines describing `parameter`, without the surrounding document.
+    fn xml(parameter: Parameter) -> Vec<String> {
+        xscreensaver_xml("test", "Test", "Testing.", [&parameter])
+            .lines()
+            .skip(2)
+            .take_while(|line| !line.contains("<_description>"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn document_around_the_parameters() {
+        let xml = xscreensaver_xml("test", "A <test>", "Tests & such.", []);
+        assert_eq!(
+            xml,
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n",
+                "<screensaver name=\"test\" _label=\"A &lt;test&gt;\">\n",
+                "  <_description>Tests &amp; such.</_description>\n",
+                "</screensaver>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn slider_xml() {
+        assert_eq!(
+            xml(SLIDER),
+            [
+                r#"  <number id="delay" type="slider" arg="-delay %" _label="Frame rate" _low-label="Low" _high-label="High" low="0" high="100" default="50" convert="invert"/>"#
+            ]
+        );
+        let mut slider = SLIDER;
+        slider.kind = Kind::Slider {
+            low: 0.5,
+            high: 2.0,
+            default: 1.0,
+            low_label: "Slow",
+            high_label: "Fast",
+            invert: false,
+        };
+        assert_eq!(
+            xml(slider),
+            [
+                r#"  <number id="delay" type="slider" arg="-delay %" _label="Frame rate" _low-label="Slow" _high-label="Fast" low="0.5" high="2" default="1"/>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn spin_xml() {
+        let spin = Parameter {
+            id: "count",
+            label: "Count",
+            arg: "-count",
+            kind: Kind::Spin {
+                low: 1.0,
+                high: 64.0,
+                default: 20.0,
+            },
+        };
+        assert_eq!(
+            xml(spin),
+            [
+                r#"  <number id="count" type="spinbutton" arg="-count %" _label="Count" low="1" high="64" default="20"/>"#
+            ]
+        );
+    }
+
+    #[test]
+    fn toggle_xml() {
+        let toggle = Parameter {
+            id: "showfps",
+            label: "Show frame rate",
+            arg: "-fps",
+            kind: Kind::Toggle,
+        };
+        assert_eq!(
+            xml(toggle),
+            [r#"  <boolean id="showfps" _label="Show frame rate" arg-set="-fps"/>"#]
+        );
+    }
+
+    #[test]
+    fn select_xml_sets_all_but_the_default() {
+        assert_eq!(
+            xml(SELECT),
+            [
+                r#"  <select id="style">"#,
+                r#"    <option id="bars" _label="Bars"/>"#,
+                r#"    <option id="grid" _label="Grid &amp; more" arg-set="-style grid"/>"#,
+                r#"  </select>"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn list_and_color_xml_are_strings() {
+        assert_eq!(
+            xml(LIST),
+            [r#"  <string id="post" _label="Effects" arg="-post %"/>"#]
+        );
+        let color = Parameter {
+            id: "color",
+            label: "Color",
+            arg: "-color",
+            kind: Kind::Color { default: "#ff00ff" },
+        };
+        assert_eq!(
+            xml(color),
+            [r#"  <string id="color" _label="Color" arg="-color %"/>"#]
+        );
+    }
+
+    #[test]
+    fn file_xml() {
+        let file = Parameter {
+            id: "camerapath",
+            label: "Camera path",
+            arg: "-camera-path",
+            kind: Kind::File,
+        };
+        assert_eq!(
+            xml(file),
+            [r#"  <file id="camerapath" _label="Camera path" arg="-camera-path %"/>"#]
+        );
+    }
+
+    #[test]
+    fn parses_valid_values() {
+        assert_eq!(SLIDER.parse(Some("12.5")), Ok(Value::Number(12.5)));
+        assert_eq!(SLIDER.parse(Some("100")), Ok(Value::Number(100.0)));
+        assert_eq!(SELECT.parse(Some("grid")), Ok(Value::Text("grid".into())));
+        assert_eq!(
+            LIST.parse(Some("crt, bloom")),
+            Ok(Value::Text("crt, bloom".into()))
+        );
+        assert_eq!(LIST.parse(Some("")), Ok(Value::Text(String::new())));
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert_eq!(SLIDER.parse(None), Err("-delay needs a value".into()));
+        assert_eq!(
+            SLIDER.parse(Some("fast")),
+            Err("invalid value for -delay: fast".into())
+        );
+        assert_eq!(
+            SLIDER.parse(Some("101")),
+            Err("-delay must be between 0 and 100, got 101".into())
+        );
+        assert!(SLIDER.parse(Some("-1")).is_err());
+        assert_eq!(
+            SELECT.parse(Some("dots")),
+            Err("invalid value for -style: dots".into())
+        );
+        assert_eq!(
+            LIST.parse(Some("bloom,blur")),
+            Err("unknown name for -post: blur".into())
+        );
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#ff0000"), Some([1.0, 0.0, 0.0]));
+        for color in ["ff0000", "#ff00", "#ff00000", "#gg0000"] {
+            assert_eq!(parse_color(color), None, "{}", color);
+        }
+    }
+}
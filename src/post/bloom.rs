@@ -0,0 +1,175 @@
+use wgpu::util::DeviceExt;
+use wgpu::{CommandEncoder, Device, TextureView};
+
+use super::{FullscreenPass, PassDescriptor, PassInputs, PostEffect, RenderTexture, HDR_FORMAT};
+use crate::args::Args;
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BloomUniform {
+    /// Brightness from which things start to glow
+    threshold: f32,
+    /// How far below the threshold the glow fades in
+    knee: f32,
+    intensity: f32,
+    // Uniforms are laid out in 16 byte chunks
+    _padding: f32,
+}
+
+/// Makes bright parts of the scene glow.
+///
+/// What's above the threshold is blurred by halving it down a chain of
+/// smaller and smaller textures and adding them back up again, then added
+/// onto the scene.
+pub struct Bloom {
+    prefilter: FullscreenPass,
+    downsample: FullscreenPass,
+    upsample: FullscreenPass,
+    composite: FullscreenPass,
+    params_buffer: wgpu::Buffer,
+    /// Half the size of the scene, then half of that and so on.
+    mips: Vec<RenderTexture>,
+    levels: u32,
+}
+
+impl Bloom {
+    pub fn new(device: &Device, width: u32, height: u32, args: &Args) -> Self {
+        let threshold = args.number("bloomthreshold") as f32;
+        let params = BloomUniform {
+            threshold,
+            knee: (threshold * 0.5).max(0.01),
+            intensity: args.number("bloomintensity") as f32,
+            _padding: 0.0,
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bloom Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let pass = |label, entry_point, blend| {
+            FullscreenPass::new(
+                device,
+                &PassDescriptor {
+                    label,
+                    source: include_str!("bloom.wgsl"),
+                    entry_point,
+                    format: HDR_FORMAT,
+                    textures: 1,
+                    params: true,
+                    blend,
+                },
+            )
+        };
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+        let composite = FullscreenPass::new(
+            device,
+            &PassDescriptor {
+                label: "Bloom Composite",
+                source: include_str!("bloom_composite.wgsl"),
+                entry_point: "fs_main",
+                format: HDR_FORMAT,
+                textures: 2,
+                params: true,
+                blend: None,
+            },
+        );
+
+        let levels = args.number("bloomlevels") as u32;
+        Self {
+            prefilter: pass("Bloom Prefilter", "fs_prefilter", None),
+            downsample: pass("Bloom Downsample", "fs_downsample", None),
+            upsample: pass("Bloom Upsample", "fs_upsample", Some(additive)),
+            composite,
+            params_buffer,
+            mips: Self::mips(device, width, height, levels),
+            levels,
+        }
+    }
+
+    /// As many of `levels` halvings as fit in the size, and at least one.
+    fn mips(device: &Device, width: u32, height: u32, levels: u32) -> Vec<RenderTexture> {
+        let mut mips = Vec::new();
+        let (mut width, mut height) = ((width / 2).max(1), (height / 2).max(1));
+        loop {
+            mips.push(RenderTexture::new(device, "Bloom Mip", width, height));
+            if mips.len() >= levels as usize || width < 4 || height < 4 {
+                return mips;
+            }
+            width /= 2;
+            height /= 2;
+        }
+    }
+}
+
+impl PostEffect for Bloom {
+    fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.mips = Self::mips(device, width, height, self.levels);
+    }
+
+    fn render(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        input: &TextureView,
+        output: &TextureView,
+    ) {
+        let params = Some(&self.params_buffer);
+        let first = &self.mips[0];
+        self.prefilter.draw(
+            device,
+            encoder,
+            PassInputs {
+                textures: &[input],
+                params,
+            },
+            &first.view,
+            true,
+        );
+        for pair in self.mips.windows(2) {
+            self.downsample.draw(
+                device,
+                encoder,
+                PassInputs {
+                    textures: &[&pair[0].view],
+                    params,
+                },
+                &pair[1].view,
+                true,
+            );
+        }
+        // Back up again, adding each level onto the next larger one
+        for pair in self.mips.windows(2).rev() {
+            self.upsample.draw(
+                device,
+                encoder,
+                PassInputs {
+                    textures: &[&pair[1].view],
+                    params,
+                },
+                &pair[0].view,
+                false,
+            );
+        }
+
+        self.composite.draw(
+            device,
+            encoder,
+            PassInputs {
+                textures: &[input, &first.view],
+                params: Some(&self.params_buffer),
+            },
+            output,
+            true,
+        );
+    }
+}
@@ -0,0 +1,64 @@
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+struct BloomUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+};
+@group(0) @binding(2)
+var<uniform> params: BloomUniform;
+
+fn texel() -> vec2<f32> {
+    return 1.0 / vec2<f32>(textureDimensions(source));
+}
+
+fn tap(uv: vec2<f32>) -> vec3<f32> {
+    return textureSample(source, source_sampler, uv).rgb;
+}
+
+// Four bilinear taps, averaging 4x4 source texels into one
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let t = texel();
+    return (tap(uv + vec2<f32>(-t.x, -t.y))
+        + tap(uv + vec2<f32>(t.x, -t.y))
+        + tap(uv + vec2<f32>(-t.x, t.y))
+        + tap(uv + vec2<f32>(t.x, t.y))) * 0.25;
+}
+
+// Fragment Shaders
+
+// The first downsample, only keeping what's brighter than the threshold.
+// The knee eases things in rather than cutting them off.
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = downsample(in.uv);
+    let brightness = max(color.r, max(color.g, color.b));
+    var soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    soft = soft * soft / (4.0 * params.knee + 0.00001);
+    let contribution = max(soft, brightness - params.threshold) / max(brightness, 0.00001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.uv), 1.0);
+}
+
+// A 3x3 tent over the smaller level, added onto the larger one
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let t = texel();
+    var color = tap(in.uv) * 4.0;
+    color = color + (tap(in.uv + vec2<f32>(-t.x, 0.0))
+        + tap(in.uv + vec2<f32>(t.x, 0.0))
+        + tap(in.uv + vec2<f32>(0.0, -t.y))
+        + tap(in.uv + vec2<f32>(0.0, t.y))) * 2.0;
+    color = color + tap(in.uv + vec2<f32>(-t.x, -t.y))
+        + tap(in.uv + vec2<f32>(t.x, -t.y))
+        + tap(in.uv + vec2<f32>(-t.x, t.y))
+        + tap(in.uv + vec2<f32>(t.x, t.y));
+    return vec4<f32>(color / 16.0, 1.0);
+}
@@ -0,0 +1,22 @@
+@group(0) @binding(0)
+var scene: texture_2d<f32>;
+@group(0) @binding(1)
+var bloom: texture_2d<f32>;
+@group(0) @binding(2)
+var linear_sampler: sampler;
+
+struct BloomUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+};
+@group(0) @binding(3)
+var<uniform> params: BloomUniform;
+
+// Fragment Shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(scene, linear_sampler, in.uv).rgb;
+    let glow = textureSample(bloom, linear_sampler, in.uv).rgb;
+    return vec4<f32>(color + glow * params.intensity, 1.0);
+}
@@ -0,0 +1,215 @@
+use wgpu::util::DeviceExt;
+use wgpu::{CommandEncoder, Device, Queue, TextureView};
+
+use super::{FullscreenPass, PassDescriptor, PassInputs, PostEffect, RenderTexture, HDR_FORMAT};
+use crate::primitives::Clock;
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CrtUniform {
+    /// How strong the effect is, what that means is up to the effect
+    amount: f32,
+    /// Seconds since we started, for effects that change over time
+    time: f32,
+    // Uniforms are laid out in 16 byte chunks
+    _padding: [f32; 2],
+}
+
+fn params_buffer(device: &Device, label: &str, amount: f32) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::cast_slice(&[CrtUniform {
+            amount,
+            time: 0.0,
+            _padding: [0.0; 2],
+        }]),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+/// One of the retro display effects in `crt.wgsl`, a single pass each.
+pub struct Crt {
+    pass: FullscreenPass,
+    params: CrtUniform,
+    params_buffer: wgpu::Buffer,
+}
+
+impl Crt {
+    /// The effect with the fragment shader `fs_<name>`.
+    pub fn new(device: &Device, name: &str, amount: f32) -> Self {
+        let pass = FullscreenPass::new(
+            device,
+            &PassDescriptor {
+                label: name,
+                source: include_str!("crt.wgsl"),
+                entry_point: &format!("fs_{}", name),
+                format: HDR_FORMAT,
+                textures: 1,
+                params: true,
+                blend: None,
+            },
+        );
+        Self {
+            pass,
+            params: CrtUniform {
+                amount,
+                time: 0.0,
+                _padding: [0.0; 2],
+            },
+            params_buffer: params_buffer(device, name, amount),
+        }
+    }
+}
+
+impl PostEffect for Crt {
+    fn update(&mut self, queue: &Queue, clock: &Clock) {
+        self.params.time = clock.elapsed() as f32;
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[self.params]));
+    }
+
+    fn render(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        input: &TextureView,
+        output: &TextureView,
+    ) {
+        self.pass.draw(
+            device,
+            encoder,
+            PassInputs {
+                textures: &[input],
+                params: Some(&self.params_buffer),
+            },
+            output,
+            true,
+        );
+    }
+}
+
+/// Frames per second `-phosphor-decay` is given at.
+const DECAY_RATE: f64 = 30.0;
+
+/// Brightness kept over `delta` seconds, when `decay` of it is kept every
+/// 1/30 s. Fading by time rather than by frame keeps trails the same length
+/// whatever the frame rate.
+fn afterglow(decay: f32, delta: f64) -> f32 {
+    decay.powf((delta * DECAY_RATE) as f32)
+}
+
+/// Lets bright things linger and fade like on a phosphor screen, by
+/// blending with what was shown last frame.
+pub struct Phosphor {
+    pass: FullscreenPass,
+    copy: FullscreenPass,
+    /// Brightness kept every 1/30 s.
+    decay: f32,
+    params: CrtUniform,
+    params_buffer: wgpu::Buffer,
+    /// The last frame's output.
+    history: RenderTexture,
+}
+
+impl Phosphor {
+    pub fn new(device: &Device, width: u32, height: u32, decay: f32) -> Self {
+        let pass = FullscreenPass::new(
+            device,
+            &PassDescriptor {
+                label: "Phosphor",
+                source: include_str!("phosphor.wgsl"),
+                entry_point: "fs_main",
+                format: HDR_FORMAT,
+                textures: 2,
+                params: true,
+                blend: None,
+            },
+        );
+        let copy = FullscreenPass::new(
+            device,
+            &PassDescriptor {
+                label: "Phosphor History",
+                source: include_str!("copy.wgsl"),
+                entry_point: "fs_main",
+                format: HDR_FORMAT,
+                textures: 1,
+                params: false,
+                blend: None,
+            },
+        );
+        Self {
+            pass,
+            copy,
+            decay,
+            params: CrtUniform {
+                amount: decay,
+                time: 0.0,
+                _padding: [0.0; 2],
+            },
+            params_buffer: params_buffer(device, "Phosphor", decay),
+            history: RenderTexture::new(device, "Phosphor History", width, height),
+        }
+    }
+}
+
+impl PostEffect for Phosphor {
+    fn update(&mut self, queue: &Queue, clock: &Clock) {
+        self.params.amount = afterglow(self.decay, clock.delta());
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[self.params]));
+    }
+
+    fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        // Starting over dark is fine, it only lasts a few frames
+        self.history = RenderTexture::new(device, "Phosphor History", width, height);
+    }
+
+    fn render(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        input: &TextureView,
+        output: &TextureView,
+    ) {
+        self.pass.draw(
+            device,
+            encoder,
+            PassInputs {
+                textures: &[input, &self.history.view],
+                params: Some(&self.params_buffer),
+            },
+            output,
+            true,
+        );
+        self.copy.draw(
+            device,
+            encoder,
+            PassInputs {
+                textures: &[output],
+                params: None,
+            },
+            &self.history.view,
+            true,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn afterglow_is_the_decay_every_thirtieth_of_a_second() {
+        assert_eq!(afterglow(0.5, 1.0 / 30.0), 0.5);
+        assert!((afterglow(0.5, 1.0 / 15.0) - 0.25).abs() < 1e-6);
+        // Four frames at 120 fps fade as much as one at 30
+        let fast = afterglow(0.5, 1.0 / 120.0).powi(4);
+        assert!((fast - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn nothing_fades_while_no_time_passes() {
+        assert_eq!(afterglow(0.7, 0.0), 1.0);
+        assert_eq!(afterglow(0.0, 1.0 / 30.0), 0.0);
+    }
+}
@@ -0,0 +1,79 @@
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+struct CrtUniform {
+    amount: f32,
+    time: f32,
+};
+@group(0) @binding(2)
+var<uniform> params: CrtUniform;
+
+let PI: f32 = 3.14159265;
+// Scanlines are this many pixels apart
+let SCANLINE_PERIOD: f32 = 3.0;
+
+fn tap(uv: vec2<f32>) -> vec3<f32> {
+    return textureSample(source, source_sampler, uv).rgb;
+}
+
+fn size() -> vec2<f32> {
+    return vec2<f32>(textureDimensions(source));
+}
+
+// Fragment Shaders
+
+// Darkens every few rows, `amount` being how much
+@fragment
+fn fs_scanlines(in: VertexOutput) -> @location(0) vec4<f32> {
+    let row = in.uv.y * size().y;
+    let dark = 0.5 + 0.5 * cos(row * 2.0 * PI / SCANLINE_PERIOD);
+    return vec4<f32>(tap(in.uv) * (1.0 - params.amount * dark), 1.0);
+}
+
+// Bulges the picture out like the glass of a tube, `amount` being the
+// curvature. The corners fall off the screen.
+@fragment
+fn fs_barrel(in: VertexOutput) -> @location(0) vec4<f32> {
+    let centered = in.uv * 2.0 - 1.0;
+    let bent = centered * (1.0 + params.amount * dot(centered, centered)) / (1.0 + params.amount);
+    let uv = bent * 0.5 + 0.5;
+    let color = tap(uv);
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+// Splits red and blue apart towards the edges, `amount` pixels at the
+// corners
+@fragment
+fn fs_aberration(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = (in.uv - 0.5) * 2.0 * params.amount / size();
+    let red = tap(in.uv + offset).r;
+    let green = tap(in.uv).g;
+    let blue = tap(in.uv - offset).b;
+    return vec4<f32>(red, green, blue, 1.0);
+}
+
+// Darkens the corners, `amount` being how much
+@fragment
+fn fs_vignette(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 0 in the middle, 1 in the corners
+    let distance = length(in.uv - 0.5) * 1.41421356;
+    let shade = 1.0 - params.amount * smoothstep(0.3, 1.0, distance);
+    return vec4<f32>(tap(in.uv) * shade, 1.0);
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// Noise changing every frame, `amount` being how strong
+@fragment
+fn fs_grain(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = floor(in.uv * size());
+    let noise = hash(pixel + fract(params.time * 7.13) * 113.0) - 0.5;
+    return vec4<f32>(max(tap(in.uv) + noise * params.amount, vec3<f32>(0.0)), 1.0);
+}
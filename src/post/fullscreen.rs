@@ -0,0 +1,201 @@
+use wgpu::{
+    BindGroupLayout, BlendState, Buffer, CommandEncoder, Device, RenderPipeline, Sampler,
+    TextureFormat, TextureView,
+};
+
+/// A full-screen triangle running a fragment shader over some input
+/// textures, the building block of every post effect.
+///
+/// The fragment shader gets `fullscreen.wgsl` prepended, and its inputs at
+/// group 0: the textures at bindings `0..textures`, a linear sampler after
+/// them and, for passes with `params`, their uniform buffer last.
+pub struct FullscreenPass {
+    pipeline: RenderPipeline,
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    textures: u32,
+}
+
+/// What a [`FullscreenPass`] reads.
+pub struct PassInputs<'a> {
+    pub textures: &'a [&'a TextureView],
+    pub params: Option<&'a Buffer>,
+}
+
+/// How to build a [`FullscreenPass`].
+#[derive(Clone, Copy)]
+pub struct PassDescriptor<'a> {
+    pub label: &'a str,
+    /// WGSL with the fragment shader.
+    pub source: &'a str,
+    pub entry_point: &'a str,
+    /// Format of the output.
+    pub format: TextureFormat,
+    /// Number of input textures.
+    pub textures: u32,
+    /// Whether the pass takes a uniform buffer.
+    pub params: bool,
+    pub blend: Option<BlendState>,
+}
+
+impl FullscreenPass {
+    pub fn new(device: &Device, descriptor: &PassDescriptor) -> Self {
+        let PassDescriptor {
+            label,
+            source,
+            entry_point,
+            format,
+            textures,
+            params,
+            blend,
+        } = *descriptor;
+        let mut entries = (0..textures)
+            .map(|binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            })
+            .collect::<Vec<_>>();
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: textures,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        });
+        if params {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: textures + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            });
+        }
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some(label),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let source = format!("{}\n{}", include_str!("fullscreen.wgsl"), source);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            layout,
+            sampler,
+            textures,
+        }
+    }
+
+    /// Run the pass over `inputs` into `output`. With `clear` the output is
+    /// cleared first, otherwise blended into as the pipeline says.
+    pub fn draw(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        inputs: PassInputs,
+        output: &TextureView,
+        clear: bool,
+    ) {
+        assert_eq!(
+            inputs.textures.len() as u32,
+            self.textures,
+            "wrong number of inputs"
+        );
+        let mut entries = inputs
+            .textures
+            .iter()
+            .enumerate()
+            .map(|(binding, view)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            })
+            .collect::<Vec<_>>();
+        entries.push(wgpu::BindGroupEntry {
+            binding: self.textures,
+            resource: wgpu::BindingResource::Sampler(&self.sampler),
+        });
+        if let Some(params) = inputs.params {
+            entries.push(wgpu::BindGroupEntry {
+                binding: self.textures + 1,
+                resource: params.as_entire_binding(),
+            });
+        }
+        // Inputs change with every resize and ping-pong, so bind groups are
+        // cheap enough to make as we go
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            entries: &entries,
+            label: None,
+        });
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if clear {
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                    } else {
+                        wgpu::LoadOp::Load
+                    },
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
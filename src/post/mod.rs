@@ -0,0 +1,397 @@
+//! Full-screen passes run over the rendered scene before it's shown.
+
+use wgpu::{CommandEncoder, Device, Queue, TextureFormat, TextureView};
+
+use crate::args::Args;
+use crate::params::{list, Kind, Parameter};
+use crate::primitives::Clock;
+
+mod bloom;
+mod crt;
+mod fullscreen;
+
+pub use bloom::Bloom;
+pub use crt::{Crt, Phosphor};
+pub use fullscreen::{FullscreenPass, PassDescriptor, PassInputs};
+
+/// Format the scene is rendered in, so bright things can go past 1 until
+/// post processing is done with them.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Format the scene is rendered in instead when [`HDR_FORMAT`] can't be
+/// multisampled. Antialiasing is worth more than what's brighter than 1,
+/// bloom still finds what's close to it.
+pub const MSAA_FALLBACK_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// `(name, label)` of every effect, `name` being what `-post` takes.
+pub const EFFECTS: &[(&str, &str)] = &[
+    ("bloom", "Bloom"),
+    ("phosphor", "Phosphor persistence"),
+    ("scanlines", "Scanlines"),
+    ("aberration", "Chromatic aberration"),
+    ("barrel", "Barrel distortion"),
+    ("vignette", "Vignette"),
+    ("grain", "Film grain"),
+];
+
+/// Effects that can be switched on with a flag of the same name as well,
+/// they go after the ones from `-post` in this order.
+const TOGGLED: &[&str] = &[
+    "phosphor",
+    "scanlines",
+    "aberration",
+    "barrel",
+    "vignette",
+    "grain",
+];
+
+pub const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        id: "post",
+        label: "Post effects, in order",
+        arg: "-post",
+        kind: Kind::List {
+            options: EFFECTS,
+            default: "bloom",
+        },
+    },
+    Parameter {
+        id: "bloomthreshold",
+        label: "Bloom threshold",
+        arg: "-bloom-threshold",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 2.0,
+            default: 0.7,
+            low_label: "Everything",
+            high_label: "Brightest",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "bloomintensity",
+        label: "Bloom intensity",
+        arg: "-bloom-intensity",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 3.0,
+            default: 0.6,
+            low_label: "Subtle",
+            high_label: "Blinding",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "bloomlevels",
+        label: "Bloom radius",
+        arg: "-bloom-levels",
+        kind: Kind::Spin {
+            low: 1.0,
+            high: 8.0,
+            default: 5.0,
+        },
+    },
+    Parameter {
+        id: "phosphor",
+        label: "Phosphor persistence",
+        arg: "-phosphor",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "phosphordecay",
+        label: "Phosphor afterglow",
+        arg: "-phosphor-decay",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 0.98,
+            default: 0.7,
+            low_label: "Short",
+            high_label: "Long",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "scanlines",
+        label: "Scanlines",
+        arg: "-scanlines",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "scanlinestrength",
+        label: "Scanline darkness",
+        arg: "-scanline-strength",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 1.0,
+            default: 0.4,
+            low_label: "Faint",
+            high_label: "Dark",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "aberration",
+        label: "Chromatic aberration",
+        arg: "-aberration",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "aberrationamount",
+        label: "Color fringe width",
+        arg: "-aberration-amount",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 10.0,
+            default: 2.0,
+            low_label: "Narrow",
+            high_label: "Wide",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "barrel",
+        label: "Barrel distortion",
+        arg: "-barrel",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "barrelamount",
+        label: "Screen curvature",
+        arg: "-barrel-amount",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 0.5,
+            default: 0.1,
+            low_label: "Flat",
+            high_label: "Round",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "vignette",
+        label: "Vignette",
+        arg: "-vignette",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "vignettestrength",
+        label: "Vignette darkness",
+        arg: "-vignette-strength",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 1.0,
+            default: 0.5,
+            low_label: "Faint",
+            high_label: "Dark",
+            invert: false,
+        },
+    },
+    Parameter {
+        id: "grain",
+        label: "Film grain",
+        arg: "-grain",
+        kind: Kind::Toggle,
+    },
+    Parameter {
+        id: "grainamount",
+        label: "Grain strength",
+        arg: "-grain-amount",
+        kind: Kind::Slider {
+            low: 0.0,
+            high: 0.5,
+            default: 0.08,
+            low_label: "Fine",
+            high_label: "Noisy",
+            invert: false,
+        },
+    },
+];
+
+/// A full-screen effect in the [`PostChain`].
+pub trait PostEffect {
+    /// Called when the scene changes size, with the new size.
+    fn resize(&mut self, _device: &Device, _width: u32, _height: u32) {}
+
+    /// Called once per frame before rendering.
+    fn update(&mut self, _queue: &Queue, _clock: &Clock) {}
+
+    /// Read the scene so far from `input` and write the result to `output`,
+    /// both the size of the scene. `output` is [`HDR_FORMAT`], `input` may
+    /// be the scene itself in [`MSAA_FALLBACK_FORMAT`].
+    fn render(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        input: &TextureView,
+        output: &TextureView,
+    );
+}
+
+/// A texture passes render into and read from.
+pub struct RenderTexture {
+    _texture: wgpu::Texture,
+    pub view: TextureView,
+}
+
+impl RenderTexture {
+    /// A texture in [`HDR_FORMAT`].
+    pub fn new(device: &Device, label: &str, width: u32, height: u32) -> Self {
+        Self::with_format(device, label, HDR_FORMAT, width, height)
+    }
+
+    pub fn with_format(
+        device: &Device,
+        label: &str,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            _texture: texture,
+            view,
+        }
+    }
+}
+
+/// The HDR scene the main pass renders into, and the effects taking it from
+/// there to the surface.
+pub struct PostChain {
+    scene: RenderTexture,
+    /// [`HDR_FORMAT`] unless that would cost us MSAA.
+    scene_format: TextureFormat,
+    /// Effects write to one and read from the other, turn about.
+    ping_pong: [RenderTexture; 2],
+    effects: Vec<Box<dyn PostEffect>>,
+    /// Brings the result into the surface's format.
+    present: FullscreenPass,
+}
+
+impl PostChain {
+    /// A chain of the effects given with `-post`, taking a scene in
+    /// `scene_format` to `format`.
+    pub fn new(
+        device: &Device,
+        scene_format: TextureFormat,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+        args: &Args,
+    ) -> Self {
+        let post = args.text("post");
+        let mut names = list(&post).collect::<Vec<_>>();
+        for name in TOGGLED {
+            if args.flag(name) && !names.contains(name) {
+                names.push(name);
+            }
+        }
+        let effects = names
+            .into_iter()
+            .map(|name| create(name, device, width, height, args))
+            .collect();
+        let present = FullscreenPass::new(
+            device,
+            &PassDescriptor {
+                label: "Present",
+                source: include_str!("present.wgsl"),
+                entry_point: "fs_main",
+                format,
+                textures: 1,
+                params: false,
+                blend: None,
+            },
+        );
+        Self {
+            scene: RenderTexture::with_format(device, "Scene", scene_format, width, height),
+            scene_format,
+            ping_pong: Self::ping_pong(device, width, height),
+            effects,
+            present,
+        }
+    }
+
+    fn ping_pong(device: &Device, width: u32, height: u32) -> [RenderTexture; 2] {
+        [
+            RenderTexture::new(device, "Post Ping", width, height),
+            RenderTexture::new(device, "Post Pong", width, height),
+        ]
+    }
+
+    /// Where the main pass should render, or resolve to with MSAA.
+    pub fn scene(&self) -> &TextureView {
+        &self.scene.view
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.scene = RenderTexture::with_format(device, "Scene", self.scene_format, width, height);
+        self.ping_pong = Self::ping_pong(device, width, height);
+        for effect in &mut self.effects {
+            effect.resize(device, width, height);
+        }
+    }
+
+    pub fn update(&mut self, queue: &Queue, clock: &Clock) {
+        for effect in &mut self.effects {
+            effect.update(queue, clock);
+        }
+    }
+
+    /// Run every effect over the scene and write the result to `output`.
+    pub fn render(&self, device: &Device, encoder: &mut CommandEncoder, output: &TextureView) {
+        let mut input = &self.scene.view;
+        for (i, effect) in self.effects.iter().enumerate() {
+            let target = &self.ping_pong[i % 2].view;
+            effect.render(device, encoder, input, target);
+            input = target;
+        }
+        self.present.draw(
+            device,
+            encoder,
+            PassInputs {
+                textures: &[input],
+                params: None,
+            },
+            output,
+            true,
+        );
+    }
+}
+
+/// Create the effect called `name`, one of [`EFFECTS`].
+fn create(
+    name: &str,
+    device: &Device,
+    width: u32,
+    height: u32,
+    args: &Args,
+) -> Box<dyn PostEffect> {
+    let amount = |id| args.number(id) as f32;
+    match name {
+        "bloom" => Box::new(Bloom::new(device, width, height, args)),
+        "phosphor" => Box::new(Phosphor::new(
+            device,
+            width,
+            height,
+            amount("phosphordecay"),
+        )),
+        "scanlines" => Box::new(Crt::new(device, name, amount("scanlinestrength"))),
+        "aberration" => Box::new(Crt::new(device, name, amount("aberrationamount"))),
+        "barrel" => Box::new(Crt::new(device, name, amount("barrelamount"))),
+        "vignette" => Box::new(Crt::new(device, name, amount("vignettestrength"))),
+        "grain" => Box::new(Crt::new(device, name, amount("grainamount"))),
+        _ => panic!("no effect called {}", name),
+    }
+}
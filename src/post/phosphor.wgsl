@@ -0,0 +1,24 @@
+@group(0) @binding(0)
+var scene: texture_2d<f32>;
+@group(0) @binding(1)
+var history: texture_2d<f32>;
+@group(0) @binding(2)
+var linear_sampler: sampler;
+
+struct CrtUniform {
+    amount: f32,
+    time: f32,
+};
+@group(0) @binding(3)
+var<uniform> params: CrtUniform;
+
+// Fragment Shader
+
+// What was lit last frame fades out instead of going dark at once,
+// keeping `amount` of its brightness, worked out from the time since then
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(scene, linear_sampler, in.uv).rgb;
+    let afterglow = textureSample(history, linear_sampler, in.uv).rgb * params.amount;
+    return vec4<f32>(max(color, afterglow), 1.0);
+}
@@ -0,0 +1,13 @@
+@group(0) @binding(0)
+var scene: texture_2d<f32>;
+@group(0) @binding(1)
+var scene_sampler: sampler;
+
+// Fragment Shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Whatever's brighter than the display can show is clipped, bloom has
+    // already spread it around by now
+    let color = textureSample(scene, scene_sampler, in.uv).rgb;
+    return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
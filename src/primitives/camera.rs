@@ -6,6 +6,7 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Camera {
     pub eye: cgmath::Point3<f32>,
     pub target: cgmath::Point3<f32>,
@@ -33,6 +34,9 @@ pub struct CameraUniform {
     // We can't use cgmath with bytemuck directly so we'll have
     // to convert the Matrix4 into a 4x4 f32 array
     view_proj: [[f32; 4]; 4],
+    /// Back from clip space to the world, for passes that work out view
+    /// rays per pixel.
+    inv_view_proj: [[f32; 4]; 4],
 }
 
 impl CameraUniform {
@@ -40,10 +44,114 @@ impl CameraUniform {
         use cgmath::SquareMatrix;
         Self {
             view_proj: cgmath::Matrix4::identity().into(),
+            inv_view_proj: cgmath::Matrix4::identity().into(),
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera) {
-        self.view_proj = camera.build_view_projection_matrix().into();
+        use cgmath::SquareMatrix;
+        let view_proj = camera.build_view_projection_matrix();
+        self.view_proj = view_proj.into();
+        // Only degenerate cameras, like one looking at its own eye, can't be
+        // inverted. Keep the last good one then.
+        if let Some(inverse) = view_proj.invert() {
+            self.inv_view_proj = inverse.into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Point3, Vector3, Vector4};
+
+    fn camera(aspect: f32) -> Camera {
+        Camera {
+            eye: (0.0, 0.0, 0.0).into(),
+            target: (0.0, 0.0, -1.0).into(),
+            up: Vector3::unit_y(),
+            aspect,
+            fovy: 90.0,
+            znear: 1.0,
+            zfar: 100.0,
+        }
+    }
+
+    /// Where `point` ends up in normalized device coordinates.
+    fn project(camera: &Camera, point: Point3<f32>) -> Vector3<f32> {
+        let clip =
+            camera.build_view_projection_matrix() * Vector4::new(point.x, point.y, point.z, 1.0);
+        clip.truncate() / clip.w
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn square_aspect_maps_fov_to_edges() {
+        // With a 90 degree fov, a point as far to the side as it is in front
+        // is at the edge of the view
+        let ndc = project(&camera(1.0), (5.0, 5.0, -5.0).into());
+        assert_close(ndc.x, 1.0);
+        assert_close(ndc.y, 1.0);
+    }
+
+    #[test]
+    fn wider_aspect_squeezes_horizontally_only() {
+        let point = (2.0, 3.0, -5.0).into();
+        let square = project(&camera(1.0), point);
+        let wide = project(&camera(2.0), point);
+        assert_close(wide.x, square.x / 2.0);
+        assert_close(wide.y, square.y);
+        assert_close(wide.z, square.z);
+    }
+
+    #[test]
+    fn taller_aspect_stretches_horizontally() {
+        let point = (2.0, 3.0, -5.0).into();
+        let square = project(&camera(1.0), point);
+        let tall = project(&camera(0.5), point);
+        assert_close(tall.x, square.x * 2.0);
+        assert_close(tall.y, square.y);
+    }
+
+    #[test]
+    fn depth_maps_to_wgpu_range() {
+        // wgpu wants depth from 0 at znear to 1 at zfar, not OpenGL's -1 to 1
+        let camera = camera(1.5);
+        assert_close(project(&camera, (0.0, 0.0, -1.0).into()).z, 0.0);
+        assert_close(project(&camera, (0.0, 0.0, -100.0).into()).z, 1.0);
+    }
+
+    #[test]
+    fn uniform_follows_camera() {
+        let mut camera = camera(1.0);
+        let mut uniform = CameraUniform::new();
+        uniform.update_view_proj(&camera);
+        let square = uniform.view_proj;
+
+        camera.aspect = 2.0;
+        uniform.update_view_proj(&camera);
+        assert_close(uniform.view_proj[0][0], square[0][0] / 2.0);
+        assert_close(uniform.view_proj[1][1], square[1][1]);
+    }
+
+    #[test]
+    fn inverse_maps_back_to_world() {
+        let camera = camera(1.5);
+        let mut uniform = CameraUniform::new();
+        uniform.update_view_proj(&camera);
+        let point = Vector4::new(2.0, 3.0, -5.0, 1.0);
+        let clip = cgmath::Matrix4::from(uniform.view_proj) * point;
+        let world = cgmath::Matrix4::from(uniform.inv_view_proj) * clip;
+        assert_close(world.x / world.w, point.x);
+        assert_close(world.y / world.w, point.y);
+        assert_close(world.z / world.w, point.z);
     }
 }
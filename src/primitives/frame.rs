@@ -1,18 +1,174 @@
+use std::time::{Duration, Instant};
+
+/// Keeps track of animation time.
+///
+/// Runs in real time, or with a fixed step per frame when frames need to be
+/// reproducible, like when rendering to files.
+pub struct Clock {
+    elapsed: f64,
+    delta: f64,
+    frame: u32,
+    last: Instant,
+    fixed_step: Option<f64>,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            elapsed: 0.0,
+            delta: 0.0,
+            frame: 0,
+            last: Instant::now(),
+            fixed_step: None,
+        }
+    }
+
+    /// A clock advancing exactly `step` every frame.
+    pub fn fixed(step: Duration) -> Self {
+        Self {
+            fixed_step: Some(step.as_secs_f64()),
+            ..Self::new()
+        }
+    }
+
+    pub fn advance(&mut self) {
+        let now = Instant::now();
+        self.delta = match self.fixed_step {
+            Some(step) => step,
+            None => now.duration_since(self.last).as_secs_f64(),
+        };
+        self.last = now;
+        self.elapsed += self.delta;
+        self.frame += 1;
+    }
+
+    /// Seconds since the first frame.
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Seconds between the previous frame and this one.
+    pub fn delta(&self) -> f64 {
+        self.delta
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Whether time moves on a fixed step per frame, however long the frame
+    /// took. Hacks can wait for slow work then, instead of skipping ahead.
+    pub fn is_fixed(&self) -> bool {
+        self.fixed_step.is_some()
+    }
+
+    /// How far into a loop of `period` seconds we are, from 0 up to 1.
+    ///
+    /// Worked out at full precision, so loops stay seamless long after the
+    /// `f32` time in [`FrameUniform`] has started to lose precision.
+    pub fn phase(&self, period: f64) -> f32 {
+        if period <= 0.0 {
+            return 0.0;
+        }
+        (self.elapsed.rem_euclid(period) / period) as f32
+    }
+}
+
 // We need this for Rust to store our data correctly for the shaders
 #[repr(C)]
 // This is so we can store this in a buffer
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct FrameUniform {
+    /// Seconds since the first frame
+    time: f32,
+    /// Seconds since the previous frame
+    delta: f32,
     frame: u32,
+    // vec2 is aligned to 8 bytes
+    _padding: u32,
+    /// Size of the render target in pixels
+    resolution: [f32; 2],
+    // Uniforms are laid out in 16 byte chunks
+    _padding2: [f32; 2],
 }
 
 impl FrameUniform {
-    pub fn new() -> Self {
-        Self { frame: 0 }
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            time: 0.0,
+            delta: 0.0,
+            frame: 0,
+            _padding: 0,
+            resolution: [width as f32, height as f32],
+            _padding2: [0.0; 2],
+        }
+    }
+
+    pub fn update(&mut self, clock: &Clock) {
+        self.time = clock.elapsed() as f32;
+        self.delta = clock.delta() as f32;
+        self.frame = clock.frame();
+    }
+
+    pub fn set_resolution(&mut self, width: u32, height: u32) {
+        self.resolution = [width as f32, height as f32];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_clock_steps_exactly() {
+        let mut clock = Clock::fixed(Duration::from_millis(250));
+        assert_eq!(
+            (clock.elapsed(), clock.delta(), clock.frame()),
+            (0.0, 0.0, 0)
+        );
+        clock.advance();
+        clock.advance();
+        assert_eq!(
+            (clock.elapsed(), clock.delta(), clock.frame()),
+            (0.5, 0.25, 2)
+        );
+        assert!(clock.is_fixed());
+    }
+
+    #[test]
+    fn real_clock_follows_time() {
+        let mut clock = Clock::new();
+        std::thread::sleep(Duration::from_millis(5));
+        clock.advance();
+        assert!(clock.delta() >= 0.005);
+        assert_eq!(clock.elapsed(), clock.delta());
+        assert_eq!(clock.frame(), 1);
+        assert!(!clock.is_fixed());
+    }
+
+    /// A clock `elapsed` seconds in.
+    fn at(elapsed: f64) -> Clock {
+        Clock {
+            elapsed,
+            ..Clock::new()
+        }
+    }
+
+    #[test]
+    fn phase_wraps_around_the_period() {
+        assert_eq!(at(0.0).phase(4.0), 0.0);
+        assert_eq!(at(1.0).phase(4.0), 0.25);
+        assert_eq!(at(4.0).phase(4.0), 0.0);
+        assert_eq!(at(9.0).phase(4.0), 0.25);
+        assert_eq!(at(-1.0).phase(4.0), 0.75);
+        // No loop at all
+        assert_eq!(at(3.0).phase(0.0), 0.0);
     }
 
-    pub fn incr_frame(&mut self) {
-        let new_frame = self.frame + 1;
-        self.frame = new_frame % 100;
+    #[test]
+    fn phase_stays_precise_after_days() {
+        // Where f32 seconds only have a resolution of a quarter second
+        let day = 24.0 * 60.0 * 60.0;
+        assert!((at(10.0 * day + 0.1).phase(1.0) - 0.1).abs() < 1e-6);
     }
 }
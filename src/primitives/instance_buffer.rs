@@ -0,0 +1,106 @@
+use wgpu::{Buffer, BufferSlice, Device, Queue};
+
+use super::InstanceRaw;
+
+/// Instances the CPU can change every frame.
+///
+/// Keeps a GPU buffer at least as big as the last upload, doubling it when
+/// more instances come along so growing a few at a time stays cheap.
+pub struct InstanceBuffer {
+    buffer: Buffer,
+    /// In instances, not bytes.
+    capacity: usize,
+    len: usize,
+}
+
+impl InstanceBuffer {
+    pub fn new(device: &Device, capacity: usize) -> Self {
+        // Empty buffers aren't allowed
+        let capacity = capacity.max(1);
+        Self {
+            buffer: Self::create(device, capacity),
+            capacity,
+            len: 0,
+        }
+    }
+
+    fn create(device: &Device, capacity: usize) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Replace the contents with `instances`, growing the buffer if they
+    /// don't fit.
+    pub fn write(&mut self, device: &Device, queue: &Queue, instances: &[InstanceRaw]) {
+        let capacity = grown(self.capacity, instances.len());
+        if capacity != self.capacity {
+            self.capacity = capacity;
+            self.buffer = Self::create(device, capacity);
+        }
+        // Goes through a staging buffer wgpu manages for us, and is copied
+        // over before the next submit
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+        self.len = instances.len();
+    }
+
+    /// Number of instances written last.
+    pub fn count(&self) -> u32 {
+        self.len as u32
+    }
+
+    /// The part of the buffer holding the instances, for `set_vertex_buffer`.
+    pub fn slice(&self) -> BufferSlice<'_> {
+        let size = (self.len.max(1) * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+        self.buffer.slice(..size)
+    }
+}
+
+/// Capacity for `len` instances, doubling `capacity` until they fit.
+fn grown(capacity: usize, len: usize) -> usize {
+    let mut capacity = capacity;
+    while capacity < len {
+        capacity *= 2;
+    }
+    capacity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Instance;
+    use crate::state::test_device;
+
+    #[test]
+    fn capacity_stays_while_instances_fit() {
+        assert_eq!(grown(8, 0), 8);
+        assert_eq!(grown(8, 8), 8);
+    }
+
+    #[test]
+    fn capacity_doubles_until_instances_fit() {
+        assert_eq!(grown(8, 9), 16);
+        assert_eq!(grown(8, 16), 16);
+        // Well past double still fits
+        assert_eq!(grown(8, 100), 128);
+        assert_eq!(grown(1, 3), 4);
+    }
+
+    #[tokio::test]
+    async fn count_follows_the_last_write() {
+        let (device, queue) = test_device().await;
+        let instances = |n| vec![Instance::default().to_raw(); n];
+        let mut buffer = InstanceBuffer::new(&device, 2);
+        assert_eq!(buffer.count(), 0);
+        buffer.write(&device, &queue, &instances(2));
+        assert_eq!(buffer.count(), 2);
+        buffer.write(&device, &queue, &instances(5));
+        assert_eq!((buffer.count(), buffer.capacity), (5, 8));
+        buffer.write(&device, &queue, &instances(0));
+        // Emptied, but the buffer is kept for next time
+        assert_eq!((buffer.count(), buffer.capacity), (0, 8));
+    }
+}
@@ -1,8 +1,10 @@
 mod camera;
 mod frame;
 mod instance;
+mod instance_buffer;
 mod vertex;
 pub use camera::*;
 pub use frame::*;
 pub use instance::*;
+pub use instance_buffer::*;
 pub use vertex::*;
@@ -0,0 +1,261 @@
+//! Pipelines whose WGSL can be edited while the hack runs.
+//!
+//! Shaders are built into the binary, but with `-reload-shaders` in a window
+//! of our own they're read from the source tree instead and rebuilt whenever
+//! the file changes. A shader that doesn't compile is logged and the pipeline
+//! built from the last good one keeps running.
+
+use std::error::Error;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use std::time::SystemTime;
+use wgpu::{Device, RenderPipeline, ShaderModule};
+
+/// A WGSL file, and its contents at build time.
+pub struct ShaderFile {
+    pub path: PathBuf,
+    pub embedded: &'static str,
+}
+
+/// The [`ShaderFile`] `$file`, relative to the file this is used in like
+/// `include_str!`.
+macro_rules! wgsl {
+    ($file:literal) => {
+        $crate::shader::ShaderFile {
+            path: std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join(file!())
+                .with_file_name($file),
+            embedded: include_str!($file),
+        }
+    };
+}
+pub(crate) use wgsl;
+
+/// Makes a pipeline from a module with the shader.
+type Build = dyn Fn(&Device, &ShaderModule) -> RenderPipeline;
+
+/// A render pipeline that can be rebuilt from a changed shader.
+pub struct ReloadablePipeline {
+    pipeline: RenderPipeline,
+    file: ShaderFile,
+    /// Whether to look for changes to the file at all.
+    watch: bool,
+    /// Modification time of the file when we last read it.
+    modified: Option<SystemTime>,
+    build: Box<Build>,
+}
+
+impl ReloadablePipeline {
+    /// Build the pipeline from the embedded shader. With `watch` it's
+    /// rebuilt from the file on the next [`reload`](Self::reload), and every
+    /// time it changes after that.
+    pub fn new<F>(device: &Device, file: ShaderFile, watch: bool, build: F) -> Self
+    where
+        F: Fn(&Device, &ShaderModule) -> RenderPipeline + 'static,
+    {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: file.path.file_name().and_then(|name| name.to_str()),
+            source: wgpu::ShaderSource::Wgsl(file.embedded.into()),
+        });
+        Self {
+            pipeline: build(device, &shader),
+            file,
+            watch,
+            modified: None,
+            build: Box::new(build),
+        }
+    }
+
+    pub fn pipeline(&self) -> &RenderPipeline {
+        &self.pipeline
+    }
+
+    /// Rebuild the pipeline if the shader file changed since we last looked.
+    pub fn reload(&mut self, device: &Device) {
+        if !self.watch {
+            return;
+        }
+        let path = &self.file.path;
+        let modified = match std::fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(e) => {
+                log::warn!("Not watching {} anymore: {}", path.display(), e);
+                self.watch = false;
+                return;
+            }
+        };
+        if self.modified == Some(modified) {
+            return;
+        }
+        let first = self.modified.replace(modified).is_none();
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                log::error!("Can't read {}: {}", path.display(), e);
+                return;
+            }
+        };
+        // Most likely what we were built with, no need to build it again
+        if first && source == self.file.embedded {
+            return;
+        }
+        match self.compile(device, &source) {
+            Ok(pipeline) => {
+                log::info!("Reloaded {}", path.display());
+                self.pipeline = pipeline;
+            }
+            Err(e) => log::error!("Keeping the last good {}:\n{}", path.display(), e),
+        }
+    }
+
+    fn compile(&self, device: &Device, source: &str) -> Result<RenderPipeline, String> {
+        let module = parse(Language::Wgsl, source, &self.file.path, 0)?;
+        create_pipeline(device, module, &*self.build)
+    }
+}
+
+/// What a shader is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Wgsl,
+    /// GLSL 450 with a `main` for the given stage.
+    Glsl(naga::ShaderStage),
+}
+
+/// Parse and validate `source`, with errors pointing at the line and column
+/// in `path` as `path:line:column: error`. `source` may be the file with
+/// `prelude_lines` lines put in front, they're not counted.
+pub fn parse(
+    language: Language,
+    source: &str,
+    path: &Path,
+    prelude_lines: u32,
+) -> Result<naga::Module, String> {
+    // Anything in the prelude can't be pointed at in the file
+    let at = |location: Option<naga::SourceLocation>| match location {
+        Some(location) if location.line_number > prelude_lines => format!(
+            "{}:{}:{}",
+            path.display(),
+            location.line_number - prelude_lines,
+            location.line_position
+        ),
+        _ => path.display().to_string(),
+    };
+    let module = match language {
+        Language::Wgsl => naga::front::wgsl::parse_str(source)
+            .map_err(|e| format!("{}: {}", at(e.location(source)), e))?,
+        Language::Glsl(stage) => naga::front::glsl::Parser::default()
+            .parse(&stage.into(), source)
+            .map_err(|errors| {
+                errors
+                    .iter()
+                    .map(|e| format!("{}: {}", at(Some(e.meta.location(source))), e.kind))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })?,
+    };
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| {
+        let mut message = error_chain(&e);
+        for (span, label) in e.spans() {
+            message.push_str(&format!(
+                "\n  {}: {}",
+                at(Some(span.location(source))),
+                label
+            ));
+        }
+        message
+    })?;
+    Ok(module)
+}
+
+/// Build a pipeline from a module [`parse`] accepted. wgpu's own errors, like
+/// a binding that isn't in the layout, are returned rather than handed to
+/// its error handler, which panics.
+pub fn create_pipeline(
+    device: &Device,
+    module: naga::Module,
+    build: &dyn Fn(&Device, &ShaderModule) -> RenderPipeline,
+) -> Result<RenderPipeline, String> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Naga(module),
+    });
+    let pipeline = build(device, &shader);
+    // Native backends have the error at hand, the future is ready
+    let mut popped = pin!(device.pop_error_scope());
+    match popped
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        // Already lists what caused it
+        Poll::Ready(Some(e)) => Err(e.to_string()),
+        _ => Ok(pipeline),
+    }
+}
+
+/// An error and whatever caused it, a line each.
+fn error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(&format!("\n  caused by: {}", error));
+        source = error.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRELUDE: &str = "#version 450\nlayout(location = 0) out vec4 color;\n";
+
+    fn wgsl(source: &str) -> Result<naga::Module, String> {
+        parse(Language::Wgsl, source, Path::new("test.wgsl"), 0)
+    }
+
+    fn glsl(source: &str) -> Result<naga::Module, String> {
+        parse(
+            Language::Glsl(naga::ShaderStage::Fragment),
+            &format!("{}{}", PRELUDE, source),
+            Path::new("test.glsl"),
+            2,
+        )
+    }
+
+    #[test]
+    fn accepts_valid_wgsl() {
+        wgsl("fn f() -> f32 {\n    return 1.0;\n}\n").expect("valid");
+    }
+
+    #[test]
+    fn parse_errors_have_a_line() {
+        let error = wgsl("fn f() -> f32 {\n    return 1.0\n}\n").unwrap_err();
+        assert!(error.starts_with("test.wgsl:3:"), "{}", error);
+    }
+
+    #[test]
+    fn validation_errors_point_at_the_line() {
+        let error = wgsl("fn f() -> f32 {\n    return 1;\n}\n").unwrap_err();
+        assert!(error.contains("\n  test.wgsl:"), "{}", error);
+    }
+
+    #[test]
+    fn accepts_valid_glsl() {
+        glsl("void main() {\n    color = vec4(1.0);\n}\n").expect("valid");
+    }
+
+    #[test]
+    fn errors_point_into_the_file_past_the_prelude() {
+        let error = glsl("void main() {\n    color = vec4(1.0) +;\n}\n").unwrap_err();
+        assert!(error.starts_with("test.glsl:2:"), "{}", error);
+    }
+}
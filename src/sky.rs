@@ -0,0 +1,149 @@
+use wgpu::util::DeviceExt;
+use wgpu::{Device, RenderPass};
+
+use crate::args::Args;
+use crate::hacks::HackContext;
+use crate::params::parse_color;
+use crate::shader::{wgsl, ReloadablePipeline};
+
+// We need this for Rust to store our data correctly for the shaders
+#[repr(C)]
+// This is so we can store this in a buffer
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyUniform {
+    top: [f32; 4],
+    horizon: [f32; 4],
+    sun: [f32; 4],
+    /// Radius of the sun as a slope, 0 for no sun
+    sun_size: f32,
+    /// 1 to draw stars
+    stars: f32,
+    // Uniforms are laid out in 16 byte chunks
+    _padding: [f32; 2],
+}
+
+/// The background behind hacks that want one: a sky gradient with a banded
+/// sun sitting on the horizon, and maybe some stars.
+///
+/// Drawn first in the main pass, without touching the depth buffer.
+pub struct Sky {
+    pipeline: ReloadablePipeline,
+    _params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+}
+
+impl Sky {
+    pub fn new(context: &HackContext, args: &Args) -> Self {
+        let device = context.device;
+        let color = |id| {
+            let [r, g, b] = parse_color(&args.text(id)).expect("validated when parsing");
+            [r, g, b, 1.0]
+        };
+        let params = SkyUniform {
+            top: color("skytop"),
+            horizon: color("skyhorizon"),
+            sun: color("suncolor"),
+            sun_size: args.number("sunsize") as f32,
+            stars: if args.flag("stars") { 1.0 } else { 0.0 },
+            _padding: [0.0; 2],
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sky Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("sky_params_bind_group_layout"),
+            });
+
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("sky_params_bind_group"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                context.frame_bind_group_layout,
+                context.camera_bind_group_layout,
+                &params_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let (format, depth_format, sample_count) =
+            (context.format, context.depth_format, context.sample_count);
+        let pipeline = ReloadablePipeline::new(
+            device,
+            wgsl!("sky.wgsl"),
+            context.reload_shaders,
+            move |device, shader| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Sky Pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    // The sky is infinitely far away, everything else goes in front
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: depth_format,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::Always,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: sample_count,
+                        ..wgpu::MultisampleState::default()
+                    },
+                    multiview: None,
+                })
+            },
+        );
+
+        Self {
+            pipeline,
+            _params_buffer: params_buffer,
+            params_bind_group,
+        }
+    }
+
+    pub fn update(&mut self, device: &Device) {
+        self.pipeline.reload(device);
+    }
+
+    /// Expects the frame and camera uniforms bound at groups 0 and 1.
+    pub fn render<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+        rpass.set_pipeline(self.pipeline.pipeline());
+        rpass.set_bind_group(2, &self.params_bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,104 @@
+struct FrameUniform {
+    time: f32,
+    delta: f32,
+    frame: u32,
+    resolution: vec2<f32>,
+};
+@group(0) @binding(0)
+var<uniform> frame: FrameUniform;
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct SkyUniform {
+    top: vec4<f32>,
+    horizon: vec4<f32>,
+    sun: vec4<f32>,
+    sun_size: f32,
+    stars: f32,
+};
+@group(2) @binding(0)
+var<uniform> params: SkyUniform;
+
+// Height of the sun's center above the horizon, as a slope
+let SUN_HEIGHT: f32 = 0.07;
+// Number of cutouts across the lower part of the sun
+let SUN_BANDS: f32 = 7.0;
+// Cells per unit of slope the stars are scattered in
+let STAR_DENSITY: f32 = 60.0;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+// Vertex Shader
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // One triangle big enough to cover the whole screen
+    let ndc = vec2<f32>(f32(index / 2u) * 4.0 - 1.0, f32(index % 2u) * 4.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+fn hash(cell: vec2<f32>) -> f32 {
+    return fract(sin(dot(cell, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+// Fragment Shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The view ray through this pixel, from the near to the far plane
+    let near = camera.inv_view_proj * vec4<f32>(in.ndc, 0.0, 1.0);
+    let far = camera.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let ray = normalize(far.xyz / far.w - near.xyz / near.w);
+
+    let elevation = ray.y;
+    // Brightest at the horizon, below it is the floor, which is kept dark
+    var color = mix(params.horizon.rgb, params.top.rgb, smoothstep(0.0, 0.3, elevation));
+    color = mix(color, params.top.rgb, 1.0 - smoothstep(-0.05, 0.0, elevation));
+
+    // Stars and sun sit on a plane far ahead, looking down -z
+    let forward = max(-ray.z, 0.0001);
+    let plane = ray.xy / forward;
+
+    if (params.stars > 0.5 && elevation > 0.0) {
+        let cell = floor(plane * STAR_DENSITY);
+        let star = hash(cell);
+        if (star > 0.985) {
+            let center = (cell + 0.5) / STAR_DENSITY;
+            let size = 0.15 / STAR_DENSITY;
+            let twinkle = 0.6 + 0.4 * sin(frame.time * (1.0 + star * 3.0) + star * 100.0);
+            let brightness = 1.0 - smoothstep(0.0, size, distance(plane, center));
+            // Fade out towards the horizon haze
+            color = color + vec3<f32>(brightness * twinkle * smoothstep(0.0, 0.2, elevation));
+        }
+    }
+
+    let sun = (plane - vec2<f32>(0.0, SUN_HEIGHT)) / max(params.sun_size, 0.0001);
+    let radius = length(sun);
+    // Derivatives have to be taken outside of branches
+    let pixel = fwidth(radius);
+    if (params.sun_size > 0.0 && ray.z < 0.0 && elevation > 0.0) {
+        // Glow around the sun, fading into the sky
+        color = color + params.sun.rgb * 0.25 * exp(-max(radius - 1.0, 0.0) * 3.0);
+        // Cutouts from a little above the middle down, thicker towards the
+        // bottom
+        let below = clamp((0.3 - sun.y) / 1.3, 0.0, 1.0);
+        let band = fract(below * SUN_BANDS) < below * 0.8;
+        if (radius < 1.0 && !band) {
+            // Antialias the rim with the size of a pixel
+            let edge = 1.0 - smoothstep(1.0 - pixel, 1.0, radius);
+            let gradient = mix(params.sun.rgb, params.horizon.rgb * 2.0, clamp(0.5 - sun.y * 0.5, 0.0, 1.0));
+            color = mix(color, gradient, edge);
+        }
+    }
+
+    return vec4<f32>(color, 1.0);
+}
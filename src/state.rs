@@ -1,55 +1,119 @@
-use fps_clock::FpsClock;
-use wgpu::{util::DeviceExt, Device, Queue, Surface, SurfaceConfiguration};
+use std::time::Instant;
+use tokio::time::{Interval, MissedTickBehavior};
+use wgpu::{util::DeviceExt, Adapter, Device, Queue, Surface, SurfaceConfiguration};
 
 use crate::{
-    ground::Ground,
-    primitives::{Camera, CameraUniform, FrameUniform},
-    xscreensaver::SizedWindow,
+    args::Args,
+    camera_path::CameraPath,
+    depth::{DepthTexture, DEPTH_FORMAT},
+    hacks::{self, Hack, HackContext, UpdateContext},
+    msaa::{scene_format, MultisampleTarget},
+    offscreen::{OffscreenTarget, OFFSCREEN_FORMAT},
+    post::PostChain,
+    primitives::{Camera, CameraUniform, Clock, FrameUniform},
+    sky::Sky,
+    surface::{acquire_frame, RenderError, WindowSurface},
+    xscreensaver::{Event, SizedWindow},
 };
 
-pub struct State<'a> {
+/// Where frames end up.
+enum Target {
+    Window(Surface),
+    Offscreen(OffscreenTarget),
+}
+
+pub struct State {
     // WGPU
-    surface: Surface,
+    target: Target,
     pub device: Device,
     queue: Queue,
     config: SurfaceConfiguration,
+    depth: DepthTexture,
+    /// Format of the scene the main pass renders
+    scene_format: wgpu::TextureFormat,
+    sample_count: u32,
+    /// Rendered into and resolved to the scene when multisampling
+    msaa: Option<MultisampleTarget>,
+    /// Takes the HDR scene to the target
+    post: PostChain,
     // FPS
-    fps: FpsClock,
+    fps: Interval,
+    show_fps: bool,
+    fps_report: (Instant, u32),
+    clock: Clock,
     frame_uniform: FrameUniform,
     frame_buffer: wgpu::Buffer,
     frame_bind_group: wgpu::BindGroup,
     // Render
-    _camera: Camera,
-    _camera_uniform: CameraUniform,
-    _camera_buffer: wgpu::Buffer,
+    camera: Camera,
+    camera_path: Option<CameraPath>,
+    camera_uniform: CameraUniform,
+    camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
     // Assets
-    ground: Ground<'a>,
+    /// Only for hacks that want it, see [`Hack::wants_sky`]
+    sky: Option<Sky>,
+    hack: Box<dyn Hack>,
 }
 
-impl<'a> State<'a> {
-    pub async fn setup<T>(window: &T, fps: u32) -> State<'a>
+impl State {
+    /// Render into `window`, following changes to shaders on disk if
+    /// `reload_shaders`.
+    pub async fn setup<T>(window: &T, reload_shaders: bool, args: &Args) -> State
     where
         T: raw_window_handle::HasRawWindowHandle + SizedWindow,
     {
         let instance = wgpu::Instance::new(wgpu::Backends::all());
         let surface = unsafe { instance.create_surface(&window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                // Request an adapter which can render to our surface
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .expect("Failed to find an appropriate adapter");
+        // Request an adapter which can render to our surface
+        let adapter = request_adapter(&instance, Some(&surface), args.software).await;
+        let swapchain_format = surface.get_supported_formats(&adapter)[0];
+        let (width, height) = window.size();
+        Self::init(
+            adapter,
+            Some(surface),
+            swapchain_format,
+            width,
+            height,
+            reload_shaders,
+            args,
+        )
+        .await
+    }
+
+    /// Render into a texture, no window or display needed.
+    pub async fn setup_offscreen(width: u32, height: u32, args: &Args) -> State {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = request_adapter(&instance, None, args.software).await;
+        Self::init(adapter, None, OFFSCREEN_FORMAT, width, height, false, args).await
+    }
+
+    async fn init(
+        adapter: Adapter,
+        surface: Option<Surface>,
+        swapchain_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        reload_shaders: bool,
+        args: &Args,
+    ) -> State {
+        let (scene_format, sample_count) = scene_format(&adapter, args.sample_count());
+        // Without this wgpu holds every format to what WebGPU guarantees,
+        // which doesn't include filtering 32 bit floats
+        let features =
+            adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+        let float32_filterable = !features.is_empty()
+            && adapter
+                .get_texture_format_features(wgpu::TextureFormat::Rgba32Float)
+                .flags
+                .contains(wgpu::TextureFormatFeatureFlags::FILTERABLE);
 
         // Create the logical device and command queue
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    features: wgpu::Features::empty(),
+                    features,
                     limits: wgpu::Limits::default(),
                 },
                 None,
@@ -57,9 +121,6 @@ impl<'a> State<'a> {
             .await
             .expect("Failed to create device");
 
-        let swapchain_format = surface.get_supported_formats(&adapter)[0];
-        let (width, height) = window.size();
-
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: swapchain_format,
@@ -68,7 +129,12 @@ impl<'a> State<'a> {
             present_mode: wgpu::PresentMode::Fifo,
         };
 
-        let frame_uniform = FrameUniform::new();
+        // Files rendered frame by frame should come out the same every time
+        let clock = match args.render_frames {
+            Some(_) => Clock::fixed(args.delay()),
+            None => Clock::new(),
+        };
+        let frame_uniform = FrameUniform::new(width, height);
 
         let frame_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Frame Buffer"),
@@ -80,7 +146,7 @@ impl<'a> State<'a> {
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -113,6 +179,7 @@ impl<'a> State<'a> {
             znear: 0.1,
             zfar: 1000.0,
         };
+        let camera_path = load_camera_path(&args.text("camerapath"));
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_proj(&camera);
 
@@ -126,7 +193,7 @@ impl<'a> State<'a> {
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -146,30 +213,60 @@ impl<'a> State<'a> {
             label: Some("camera_bind_group"),
         });
 
-        // Create the rendering pipeline
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&frame_bind_group_layout, &camera_bind_group_layout],
-            push_constant_ranges: &[],
-        });
+        let context = HackContext {
+            device: &device,
+            format: scene_format,
+            width,
+            height,
+            depth_format: DEPTH_FORMAT,
+            sample_count,
+            float32_filterable,
+            frame_bind_group_layout: &frame_bind_group_layout,
+            camera_bind_group_layout: &camera_bind_group_layout,
+            reload_shaders,
+        };
+        let hack = hacks::create(&args.text("hack"), &context, args);
+        let sky = hack.wants_sky().then(|| Sky::new(&context, args));
 
-        let ground = Ground::init(&device, pipeline_layout, swapchain_format);
+        let depth = DepthTexture::new(&device, width, height, sample_count);
+        let msaa = (sample_count > 1)
+            .then(|| MultisampleTarget::new(&device, scene_format, width, height, sample_count));
+        let post = PostChain::new(&device, scene_format, swapchain_format, width, height, args);
+
+        let target = match surface {
+            Some(surface) => {
+                surface.configure(&device, &config);
+                Target::Window(surface)
+            }
+            None => Target::Offscreen(OffscreenTarget::new(&device, width, height)),
+        };
 
-        surface.configure(&device, &config);
+        let mut fps = tokio::time::interval(args.delay().max(std::time::Duration::from_micros(1)));
+        fps.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
         Self {
-            surface,
+            target,
             device,
-            ground,
+            sky,
+            hack,
             queue,
             config,
-            fps: fps_clock::FpsClock::new(fps),
+            depth,
+            scene_format,
+            sample_count,
+            msaa,
+            post,
+            fps,
+            show_fps: args.show_fps(),
+            fps_report: (Instant::now(), 0),
+            clock,
             frame_uniform,
             frame_buffer,
             frame_bind_group,
-            _camera: camera,
-            _camera_uniform: camera_uniform,
-            _camera_buffer: camera_buffer,
+            camera,
+            camera_path,
+            camera_uniform,
+            camera_buffer,
             camera_bind_group,
         }
     }
@@ -178,27 +275,79 @@ impl<'a> State<'a> {
         if self.config.width != width || self.config.height != height {
             self.config.width = width;
             self.config.height = height;
-            self.surface.configure(&self.device, &self.config);
+            match &mut self.target {
+                Target::Window(surface) => surface.configure(&self.device, &self.config),
+                Target::Offscreen(target) => {
+                    *target = OffscreenTarget::new(&self.device, width, height)
+                }
+            }
+            self.resize_scene();
         }
     }
 
-    pub fn render(&mut self) {
-        let frame = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture");
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// Bring everything drawn before the target up to the size in `config`.
+    fn resize_scene(&mut self) {
+        let (width, height) = (self.config.width, self.config.height);
+        self.frame_uniform.set_resolution(width, height);
+        self.write_frame_uniform();
+        self.camera.aspect = width as f32 / height as f32;
+        self.update_camera();
+        self.depth = DepthTexture::new(&self.device, width, height, self.sample_count);
+        if self.msaa.is_some() {
+            self.msaa = Some(MultisampleTarget::new(
+                &self.device,
+                self.scene_format,
+                width,
+                height,
+                self.sample_count,
+            ));
+        }
+        self.post.resize(&self.device, width, height);
+        self.hack.resize(&self.device, width, height);
+    }
+
+    /// Draw a frame, into `window` when rendering to one.
+    pub fn render(&mut self, window: Option<&dyn SizedWindow>) -> Result<(), RenderError> {
+        let size = (self.config.width, self.config.height);
+        let (frame, view) = match &self.target {
+            Target::Window(surface) => {
+                let frame = match acquire_frame(&mut WindowSurface {
+                    surface,
+                    device: &self.device,
+                    config: &mut self.config,
+                    window: window.expect("rendering to a window needs the window"),
+                })? {
+                    Some(frame) => frame,
+                    None => return Ok(()),
+                };
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                (Some(frame), view)
+            }
+            Target::Offscreen(target) => (
+                None,
+                target
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default()),
+            ),
+        };
+        // Reconfigured to a new size along the way
+        if (self.config.width, self.config.height) != size {
+            self.resize_scene();
+        }
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.post.update(&self.queue, &self.clock);
+        self.hack.prepare(&self.device, &mut encoder);
+        let scene = self.post.scene();
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: self.msaa.as_ref().map_or(scene, |msaa| &msaa.view),
+                    resolve_target: self.msaa.as_ref().map(|_| scene),
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
                             r: 0.01,
@@ -209,35 +358,161 @@ impl<'a> State<'a> {
                         store: true,
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
             });
-            rpass.set_pipeline(&self.ground.pipeline);
             rpass.set_bind_group(0, &self.frame_bind_group, &[]);
             rpass.set_bind_group(1, &self.camera_bind_group, &[]);
-            rpass.set_vertex_buffer(0, self.ground.vertex_buffer.slice(..));
-
-            rpass.set_index_buffer(
-                self.ground.index_buffer.slice(..),
-                wgpu::IndexFormat::Uint16,
-            );
-            rpass.set_vertex_buffer(1, self.ground.instance_buffer.slice(..));
-            rpass.draw_indexed(
-                0..self.ground.num_indices,
-                0,
-                0..self.ground.instances.len() as _,
-            );
+            if let Some(sky) = &self.sky {
+                sky.render(&mut rpass);
+            }
+            self.hack.render(&mut rpass);
         }
+        self.post.render(&self.device, &mut encoder, &view);
 
         self.queue.submit(Some(encoder.finish()));
-        frame.present();
+        if let Some(frame) = frame {
+            frame.present();
+        }
+        Ok(())
     }
-    pub fn tick(&mut self) {
-        self.fps.tick();
-        self.frame_uniform.incr_frame();
+
+    /// Let the hack react to `events` and the passing of time.
+    pub fn update(&mut self, events: &[Event]) {
+        if let Some(sky) = &mut self.sky {
+            sky.update(&self.device);
+        }
+        let camera = self.camera;
+        if let Some(path) = &self.camera_path {
+            path.apply(&mut self.camera, self.clock.elapsed());
+        }
+        self.hack.update(&mut UpdateContext {
+            device: &self.device,
+            queue: &self.queue,
+            clock: &self.clock,
+            camera: &mut self.camera,
+            events,
+        });
+        if self.camera != camera {
+            self.update_camera();
+        }
+    }
+
+    /// Recompute the camera uniform after the camera changed.
+    fn update_camera(&mut self) {
+        self.camera_uniform.update_view_proj(&self.camera);
+        self.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+    }
+
+    /// The last rendered frame, when rendering offscreen.
+    pub fn capture(&self) -> Option<image::RgbaImage> {
+        match &self.target {
+            Target::Window(_) => None,
+            Target::Offscreen(target) => Some(target.capture(&self.device, &self.queue)),
+        }
+    }
+
+    /// Advance to the next frame, waiting until it's due.
+    pub async fn tick(&mut self) {
+        self.advance();
+        self.fps.tick().await;
+    }
+
+    /// Advance to the next frame right away.
+    pub fn advance(&mut self) {
+        self.clock.advance();
+        self.frame_uniform.update(&self.clock);
+        self.write_frame_uniform();
+        if self.show_fps {
+            self.report_fps();
+        }
+    }
+
+    fn write_frame_uniform(&self) {
         self.queue.write_buffer(
             &self.frame_buffer,
             0,
             bytemuck::cast_slice(&[self.frame_uniform]),
         );
     }
+
+    fn report_fps(&mut self) {
+        let (since, frames) = &mut self.fps_report;
+        *frames += 1;
+        let elapsed = since.elapsed();
+        if elapsed.as_secs() >= 1 {
+            log::info!("{:.1} FPS", *frames as f64 / elapsed.as_secs_f64());
+            *since = Instant::now();
+            *frames = 0;
+        }
+    }
+
+    /// Block until the GPU is done with everything we've submitted.
+    pub fn finish(&self) {
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+}
+
+/// The camera path in `file`, if there's one and it makes sense. A broken
+/// path isn't worth not showing anything over.
+fn load_camera_path(file: &str) -> Option<CameraPath> {
+    if file.is_empty() {
+        return None;
+    }
+    match std::fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|text| CameraPath::parse(&text))
+    {
+        Ok(path) => Some(path),
+        Err(e) => {
+            log::error!("Ignoring camera path {}: {}", file, e);
+            None
+        }
+    }
+}
+
+/// Find an adapter, falling back to a software one when there's no GPU or
+/// `force_fallback` asks for it.
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    compatible_surface: Option<&Surface>,
+    force_fallback: bool,
+) -> Adapter {
+    let options = |force_fallback_adapter| wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        compatible_surface,
+        force_fallback_adapter,
+    };
+    let adapter = match instance.request_adapter(&options(force_fallback)).await {
+        None if !force_fallback => {
+            log::warn!("No hardware adapter found, trying the fallback adapter");
+            instance.request_adapter(&options(true)).await
+        }
+        adapter => adapter,
+    }
+    .expect("Failed to find an appropriate adapter");
+    log::info!("Using adapter {:?}", adapter.get_info());
+    adapter
+}
+
+/// A device for tests that need the GPU, on the same adapter the hack
+/// would pick.
+#[cfg(test)]
+pub async fn test_device() -> (Device, Queue) {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let adapter = request_adapter(&instance, None, false).await;
+    adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await
+        .expect("a device to test with")
 }
@@ -0,0 +1,165 @@
+use std::fmt;
+use wgpu::{Device, Surface, SurfaceConfiguration, SurfaceError, SurfaceTexture};
+
+use crate::xscreensaver::SizedWindow;
+
+/// Something we can acquire frames from and reconfigure when it goes stale.
+///
+/// Abstracted so the recovery logic in [`acquire_frame`] can be exercised
+/// without a window or a GPU.
+pub trait RenderSurface {
+    type Frame;
+
+    fn acquire(&mut self) -> Result<Self::Frame, SurfaceError>;
+    fn reconfigure(&mut self);
+}
+
+/// Reasons rendering can't continue.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RenderError {
+    OutOfMemory,
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::OutOfMemory => write!(f, "the GPU ran out of memory"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Acquire the next frame, recovering from the errors we can recover from.
+///
+/// `Lost` and `Outdated` reconfigure the surface and retry once, `Timeout`
+/// skips the frame. `Ok(None)` means there's nothing to draw into this time
+/// around, only `OutOfMemory` is fatal.
+pub fn acquire_frame<S: RenderSurface>(surface: &mut S) -> Result<Option<S::Frame>, RenderError> {
+    match surface.acquire() {
+        Ok(frame) => Ok(Some(frame)),
+        Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+            log::warn!("Surface lost or outdated, reconfiguring");
+            surface.reconfigure();
+            match surface.acquire() {
+                Ok(frame) => Ok(Some(frame)),
+                Err(SurfaceError::OutOfMemory) => Err(RenderError::OutOfMemory),
+                Err(e) => {
+                    log::warn!("Skipping frame, surface still unusable: {}", e);
+                    Ok(None)
+                }
+            }
+        }
+        Err(SurfaceError::Timeout) => {
+            log::debug!("Timed out acquiring frame, skipping");
+            Ok(None)
+        }
+        Err(SurfaceError::OutOfMemory) => Err(RenderError::OutOfMemory),
+    }
+}
+
+/// The real thing, a wgpu surface configured for a device.
+pub struct WindowSurface<'s> {
+    pub surface: &'s Surface,
+    pub device: &'s Device,
+    /// Updated to the window's size when reconfiguring.
+    pub config: &'s mut SurfaceConfiguration,
+    pub window: &'s dyn SizedWindow,
+}
+
+impl RenderSurface for WindowSurface<'_> {
+    type Frame = SurfaceTexture;
+
+    fn acquire(&mut self) -> Result<Self::Frame, SurfaceError> {
+        self.surface.get_current_texture()
+    }
+
+    fn reconfigure(&mut self) {
+        // Most likely the window was resized and we haven't heard yet, the
+        // size we have would only be outdated again
+        let (width, height) = self.window.size();
+        if width > 0 && height > 0 {
+            self.config.width = width;
+            self.config.height = height;
+        }
+        self.surface.configure(self.device, self.config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Hands out scripted results, `Ok(n)` being the n-th frame.
+    struct ScriptedSurface {
+        results: VecDeque<Result<u32, SurfaceError>>,
+        reconfigured: u32,
+    }
+
+    impl ScriptedSurface {
+        fn new(results: Vec<Result<u32, SurfaceError>>) -> Self {
+            Self {
+                results: results.into(),
+                reconfigured: 0,
+            }
+        }
+    }
+
+    impl RenderSurface for ScriptedSurface {
+        type Frame = u32;
+
+        fn acquire(&mut self) -> Result<u32, SurfaceError> {
+            self.results
+                .pop_front()
+                .expect("acquired more than scripted")
+        }
+
+        fn reconfigure(&mut self) {
+            self.reconfigured += 1;
+        }
+    }
+
+    #[test]
+    fn frame_is_returned() {
+        let mut surface = ScriptedSurface::new(vec![Ok(1)]);
+        assert_eq!(acquire_frame(&mut surface), Ok(Some(1)));
+        assert_eq!(surface.reconfigured, 0);
+    }
+
+    #[test]
+    fn lost_and_outdated_reconfigure_and_retry() {
+        for error in [SurfaceError::Lost, SurfaceError::Outdated] {
+            let mut surface = ScriptedSurface::new(vec![Err(error), Ok(2)]);
+            assert_eq!(acquire_frame(&mut surface), Ok(Some(2)));
+            assert_eq!(surface.reconfigured, 1);
+        }
+    }
+
+    #[test]
+    fn failed_retry_skips_frame() {
+        let mut surface =
+            ScriptedSurface::new(vec![Err(SurfaceError::Lost), Err(SurfaceError::Lost)]);
+        assert_eq!(acquire_frame(&mut surface), Ok(None));
+        assert_eq!(surface.reconfigured, 1);
+    }
+
+    #[test]
+    fn timeout_skips_frame() {
+        let mut surface = ScriptedSurface::new(vec![Err(SurfaceError::Timeout)]);
+        assert_eq!(acquire_frame(&mut surface), Ok(None));
+        assert_eq!(surface.reconfigured, 0);
+    }
+
+    #[test]
+    fn out_of_memory_is_fatal() {
+        let mut surface = ScriptedSurface::new(vec![Err(SurfaceError::OutOfMemory)]);
+        assert_eq!(acquire_frame(&mut surface), Err(RenderError::OutOfMemory));
+
+        let mut surface = ScriptedSurface::new(vec![
+            Err(SurfaceError::Outdated),
+            Err(SurfaceError::OutOfMemory),
+        ]);
+        assert_eq!(acquire_frame(&mut surface), Err(RenderError::OutOfMemory));
+    }
+}
@@ -0,0 +1,382 @@
+//! Images loaded from disk into textures hacks can sample, like logos,
+//! photos and sprite sheets.
+//!
+//! Textures are sRGB with a full chain of mipmaps, so they stay smooth when
+//! drawn smaller than they are. [`Texture::load`] does it all at once, for
+//! setting up. Between frames it's too slow: [`decode`] the image and make
+//! its [`mipmaps`] on another thread, like the slideshow's loader, then
+//! upload them with [`Texture::from_mipmaps`]. Bind textures with
+//! [`bind_group_layout`]: the texture at binding 0 and its sampler at
+//! binding 1.
+
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use std::num::NonZeroU32;
+use std::path::Path;
+use wgpu::{BindGroup, BindGroupLayout, Device, Queue, Sampler, TextureFormat, TextureView};
+
+/// Format of loaded textures, images are stored in sRGB.
+pub const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// An image on the GPU, and how to sample it.
+pub struct Texture {
+    _texture: wgpu::Texture,
+    pub view: TextureView,
+    pub sampler: Sampler,
+    /// Size of the image in pixels.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Texture {
+    /// Load the PNG or JPEG at `path`, for a logo or a sprite sheet. Too
+    /// slow to do between frames, see the slideshow's loader for that.
+    // No hack has a logo or a sprite sheet yet, only the tests load one
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn load(device: &Device, queue: &Queue, path: &Path) -> Result<Self, String> {
+        let image = decode(path, device.limits().max_texture_dimension_2d)?;
+        let label = path.display().to_string();
+        Ok(Self::from_mipmaps(device, queue, &mipmaps(&image), &label))
+    }
+
+    /// Upload an image made into [`mipmaps`] already.
+    pub fn from_mipmaps(
+        device: &Device,
+        queue: &Queue,
+        levels: &[image::RgbaImage],
+        label: &str,
+    ) -> Self {
+        let (width, height) = levels[0].dimensions();
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        for (level, image) in levels.iter().enumerate() {
+            let (width, height) = image.dimensions();
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                image,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(4 * width),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Self {
+            _texture: texture,
+            view,
+            sampler,
+            width,
+            height,
+        }
+    }
+
+    /// Width over height.
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// A bind group with the texture and its sampler, for `layout` made by
+    /// [`bind_group_layout`].
+    pub fn bind_group(&self, device: &Device, layout: &BindGroupLayout) -> BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: Some("texture_bind_group"),
+        })
+    }
+}
+
+/// Layout of a [`Texture::bind_group`], visible to fragment shaders.
+pub fn bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label: Some("texture_bind_group_layout"),
+    })
+}
+
+/// `image` and every mipmap level below it, each half the size of the one
+/// before down to 1x1.
+///
+/// Made on the CPU, the GL backend can't render into one level of a texture
+/// while reading another. Averaged in linear light like the GPU filters the
+/// texture, averaging sRGB values would darken fine bright detail.
+pub fn mipmaps(image: &image::RgbaImage) -> Vec<image::RgbaImage> {
+    let table = srgb_to_linear_table();
+    let mut linear = image::Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        image::Rgba([
+            table[r as usize],
+            table[g as usize],
+            table[b as usize],
+            a as f32 / 255.0,
+        ])
+    });
+    let mut levels = vec![image.clone()];
+    for _ in 1..mip_level_count(image.width(), image.height()) {
+        let width = (linear.width() / 2).max(1);
+        let height = (linear.height() / 2).max(1);
+        // Each pixel averages the four above it
+        linear = image::imageops::resize(&linear, width, height, FilterType::Triangle);
+        let level = image::RgbaImage::from_fn(width, height, |x, y| {
+            let [r, g, b, a] = linear.get_pixel(x, y).0;
+            image::Rgba([
+                linear_to_srgb(r),
+                linear_to_srgb(g),
+                linear_to_srgb(b),
+                (a * 255.0).round() as u8,
+            ])
+        });
+        levels.push(level);
+    }
+    levels
+}
+
+/// Linear value of every 8 bit sRGB one.
+fn srgb_to_linear_table() -> [f32; 256] {
+    let mut table = [0.0; 256];
+    for (i, value) in table.iter_mut().enumerate() {
+        let c = i as f32 / 255.0;
+        *value = if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        };
+    }
+    table
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let srgb = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round() as u8
+}
+
+/// Read the image at `path`, no larger than `max_size` on either side and
+/// turned the way its EXIF orientation says.
+pub fn decode(path: &Path, max_size: u32) -> Result<RgbaImage, String> {
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let bytes = std::fs::read(path).map_err(|e| error(&e))?;
+    let mut image = image::load_from_memory(&bytes).map_err(|e| error(&e))?;
+    if image.width() > max_size || image.height() > max_size {
+        image = image.resize(max_size, max_size, FilterType::Triangle);
+    }
+    Ok(orient(image.to_rgba8(), orientation(&bytes)))
+}
+
+/// The EXIF orientation of the image in `bytes`, 1 when it has none.
+fn orientation(bytes: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(bytes))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+                .value
+                .get_uint(0)
+        })
+        .unwrap_or(1)
+}
+
+/// Turn `image`, stored with EXIF `orientation`, the right way up.
+fn orient(image: RgbaImage, orientation: u32) -> RgbaImage {
+    match orientation {
+        2 => imageops::flip_horizontal(&image),
+        3 => imageops::rotate180(&image),
+        4 => imageops::flip_vertical(&image),
+        // Mirrored along the diagonal
+        5 => imageops::flip_horizontal(&imageops::rotate90(&image)),
+        6 => imageops::rotate90(&image),
+        7 => imageops::flip_horizontal(&imageops::rotate270(&image)),
+        8 => imageops::rotate270(&image),
+        _ => image,
+    }
+}
+
+/// Mipmaps down to 1x1 for an image this size.
+fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_device;
+    use image::Rgba;
+
+    const LEFT: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const RIGHT: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    /// Pixels top to bottom, left to right, of a two pixel wide image
+    /// turned by `orientation`.
+    fn oriented(orientation: u32) -> (u32, u32, Vec<Rgba<u8>>) {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, LEFT);
+        image.put_pixel(1, 0, RIGHT);
+        let image = orient(image, orientation);
+        (
+            image.width(),
+            image.height(),
+            image.pixels().copied().collect(),
+        )
+    }
+
+    #[test]
+    fn upright_images_are_left_alone() {
+        assert_eq!(oriented(1), (2, 1, vec![LEFT, RIGHT]));
+        // Not an orientation there is
+        assert_eq!(oriented(9), (2, 1, vec![LEFT, RIGHT]));
+    }
+
+    #[test]
+    fn flips_and_half_turns() {
+        assert_eq!(oriented(2), (2, 1, vec![RIGHT, LEFT]));
+        assert_eq!(oriented(3), (2, 1, vec![RIGHT, LEFT]));
+        assert_eq!(oriented(4), (2, 1, vec![LEFT, RIGHT]));
+    }
+
+    #[test]
+    fn quarter_turns_stand_the_image_up() {
+        // Stored lying on its side, the top of the picture on the left
+        assert_eq!(oriented(6), (1, 2, vec![LEFT, RIGHT]));
+        assert_eq!(oriented(8), (1, 2, vec![RIGHT, LEFT]));
+        assert_eq!(oriented(5), (1, 2, vec![LEFT, RIGHT]));
+        assert_eq!(oriented(7), (1, 2, vec![RIGHT, LEFT]));
+    }
+
+    #[test]
+    fn mipmaps_go_down_to_one_pixel() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(2, 2), 2);
+        assert_eq!(mip_level_count(256, 256), 9);
+        // The longer side decides, the other stops at 1
+        assert_eq!(mip_level_count(640, 480), 10);
+        assert_eq!(mip_level_count(1, 1000), 10);
+    }
+
+    #[test]
+    fn mipmaps_halve_each_level() {
+        let image = image::RgbaImage::from_pixel(5, 3, image::Rgba([10, 20, 30, 255]));
+        let sizes = mipmaps(&image)
+            .iter()
+            .map(|level| level.dimensions())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [(5, 3), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn mipmaps_average_in_linear_light() {
+        // Half black and half white is 50% grey, which sRGB stores as 188,
+        // not the 128 averaging the stored values gives
+        let image = image::RgbaImage::from_fn(2, 2, |x, _| {
+            image::Rgba(if x == 0 { [0, 0, 0, 255] } else { [255; 4] })
+        });
+        let levels = mipmaps(&image);
+        assert_eq!(levels[1].get_pixel(0, 0).0, [188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        let table = srgb_to_linear_table();
+        for value in 0..=255 {
+            assert_eq!(linear_to_srgb(table[value as usize]), value);
+        }
+    }
+
+    #[test]
+    fn decodes_no_larger_than_asked() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/slideshow/1-rings.png");
+        let image = decode(&path, 16).expect("the test image decodes");
+        assert_eq!(image.width().max(image.height()), 16);
+    }
+
+    #[test]
+    fn errors_name_the_file() {
+        let missing = Path::new("no/such/photo.jpg");
+        let error = decode(missing, 1024).expect_err("there's no such file");
+        assert!(error.starts_with("no/such/photo.jpg: "), "{}", error);
+
+        let not_an_image = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let error = decode(&not_an_image, 1024).expect_err("a manifest isn't an image");
+        assert!(error.contains("Cargo.toml: "), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn loads_images_onto_the_gpu() {
+        let (device, queue) = test_device().await;
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/slideshow/1-rings.png");
+        let decoded = decode(&path, u32::MAX).expect("the test image decodes");
+        let texture = Texture::load(&device, &queue, &path).expect("the test image loads");
+        assert_eq!((texture.width, texture.height), decoded.dimensions());
+    }
+
+    #[tokio::test]
+    async fn load_errors_name_the_file() {
+        let (device, queue) = test_device().await;
+        let not_an_image = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let error = Texture::load(&device, &queue, &not_an_image)
+            .err()
+            .expect("a manifest isn't an image");
+        assert!(error.contains("Cargo.toml: "), "{}", error);
+    }
+}
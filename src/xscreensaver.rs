@@ -1,14 +1,47 @@
 #[link(name = "X11")]
 extern "C" {}
+use crate::args::{parse_window_id, Geometry, WindowTarget};
 use std::ffi::CString;
 use std::mem::MaybeUninit;
 use x11::xlib::{
-    Display, Window, XBlackPixelOfScreen, XCreateSimpleWindow, XDefaultScreenOfDisplay,
-    XGetWindowAttributes, XMapWindow, XOpenDisplay, XRootWindowOfScreen, XWindowAttributes,
+    Display, Window, XBlackPixelOfScreen, XCloseDisplay, XCreateSimpleWindow,
+    XDefaultScreenOfDisplay, XDestroyWindow, XGetWindowAttributes, XMapWindow, XOpenDisplay,
+    XRootWindowOfScreen, XUnmapWindow, XWindowAttributes,
 };
 
+#[derive(Debug, Clone, Copy)]
 pub enum Event {
-    Resized { width: u32, height: u32 },
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    KeyPressed {
+        keycode: u32,
+        keysym: u64,
+    },
+    /// Pointer events are only reported in a window of our own, positions
+    /// are in pixels from its top left.
+    PointerMoved {
+        x: i32,
+        y: i32,
+    },
+    ButtonPressed {
+        x: i32,
+        y: i32,
+    },
+    ButtonReleased {
+        x: i32,
+        y: i32,
+    },
+    /// The window needs to be redrawn.
+    Exposed,
+    /// Fully obscured windows have nothing to show, unobscured or partially
+    /// obscured ones do.
+    VisibilityChanged {
+        obscured: bool,
+    },
+    /// The window is gone, most likely xscreensaver is done with us.
+    Destroyed,
 }
 
 pub trait SizedWindow {
@@ -18,73 +51,99 @@ pub trait SizedWindow {
 pub struct ScreensaverWindow {
     pub dpy: *mut Display,
     pub root_window_id: Window,
+    /// Whether we created the window ourselves, and so should get rid of it
+    owns_window: bool,
 }
 
 impl ScreensaverWindow {
-    pub fn new() -> Result<Self, ()> {
+    pub fn new(target: WindowTarget, geometry: Geometry) -> Result<Self, ()> {
         unsafe {
-            let xscreensaver_id_str = std::env::var("XSCREENSAVER_WINDOW")
-                .ok()
-                .unwrap_or_default()
-                .split_whitespace()
-                .next()
-                .unwrap_or("")
-                .trim_start_matches("0x")
-                .to_string();
-            let xscreensaver_id = Window::from_str_radix(&xscreensaver_id_str, 16).ok();
+            let xscreensaver_id = std::env::var("XSCREENSAVER_WINDOW").ok().and_then(|value| {
+                value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|id| parse_window_id(id).ok())
+            });
             let display_num = CString::new("DISPLAY").expect("can create CString");
             let dpy = XOpenDisplay(libc::getenv(display_num.as_ptr()));
+            if dpy.is_null() {
+                return Err(());
+            }
+
+            let existing_id = match target {
+                WindowTarget::Auto => xscreensaver_id,
+                WindowTarget::Window => None,
+                // xscreensaver's window is the virtual root when we have one
+                WindowTarget::Root => Some(
+                    xscreensaver_id
+                        .unwrap_or_else(|| XRootWindowOfScreen(XDefaultScreenOfDisplay(dpy))),
+                ),
+                WindowTarget::Id(id) => Some(id),
+            };
 
-            match xscreensaver_id {
-                // We got our window from xscreensaver
+            match existing_id {
+                // We got our window from xscreensaver, or were told which one to use
                 Some(root_window_id) => {
                     x11::xlib::XSelectInput(
                         dpy,
                         root_window_id,
                         x11::xlib::ExposureMask
                             | x11::xlib::KeyPressMask
-                            | x11::xlib::StructureNotifyMask,
+                            | x11::xlib::StructureNotifyMask
+                            | x11::xlib::VisibilityChangeMask,
                     );
                     Ok(ScreensaverWindow {
                         dpy,
                         root_window_id,
+                        owns_window: false,
                     })
                 }
                 // We create our own window for development
                 None => {
-                    let height = 800;
-                    let width = 1200;
                     let screen = XDefaultScreenOfDisplay(dpy);
 
                     let win = XCreateSimpleWindow(
                         dpy,
                         XRootWindowOfScreen(screen),
-                        0,
-                        0,
-                        width,
-                        height,
+                        geometry.x,
+                        geometry.y,
+                        geometry.width,
+                        geometry.height,
                         10,
                         XBlackPixelOfScreen(screen),
                         XBlackPixelOfScreen(screen),
                     );
 
+                    // Only one client may select button presses on a window,
+                    // so we only ask for them on our own
                     x11::xlib::XSelectInput(
                         dpy,
                         win,
                         x11::xlib::ExposureMask
                             | x11::xlib::KeyPressMask
-                            | x11::xlib::StructureNotifyMask,
+                            | x11::xlib::ButtonPressMask
+                            | x11::xlib::ButtonReleaseMask
+                            | x11::xlib::PointerMotionMask
+                            | x11::xlib::StructureNotifyMask
+                            | x11::xlib::VisibilityChangeMask,
                     );
                     XMapWindow(dpy, win);
                     Ok(ScreensaverWindow {
                         dpy,
                         root_window_id: win,
+                        owns_window: true,
                     })
                 }
             }
         }
     }
 
+    /// Whether this is a window we created for development, rather than one
+    /// we were given.
+    pub fn owns_window(&self) -> bool {
+        self.owns_window
+    }
+
     pub fn process_events(&self) -> Vec<Event> {
         let mut result: Vec<Event> = Vec::new();
         let mut cur_xevent = x11::xlib::XEvent { pad: [0; 24] };
@@ -99,7 +158,45 @@ impl ScreensaverWindow {
                         height: e.height as u32,
                     });
                 }
-                x11::xlib::KeyPress => {}
+                x11::xlib::KeyPress => {
+                    let mut e = x11::xlib::XKeyEvent::from(cur_xevent);
+                    let keysym = unsafe { x11::xlib::XLookupKeysym(&mut e, 0) };
+                    result.push(Event::KeyPressed {
+                        keycode: e.keycode,
+                        keysym: keysym as u64,
+                    });
+                }
+                x11::xlib::MotionNotify => {
+                    let e = x11::xlib::XMotionEvent::from(cur_xevent);
+                    result.push(Event::PointerMoved { x: e.x, y: e.y });
+                }
+                x11::xlib::ButtonPress => {
+                    let e = x11::xlib::XButtonEvent::from(cur_xevent);
+                    result.push(Event::ButtonPressed { x: e.x, y: e.y });
+                }
+                x11::xlib::ButtonRelease => {
+                    let e = x11::xlib::XButtonEvent::from(cur_xevent);
+                    result.push(Event::ButtonReleased { x: e.x, y: e.y });
+                }
+                x11::xlib::Expose => {
+                    let e = x11::xlib::XExposeEvent::from(cur_xevent);
+                    // Only the last in a series of expose events, no need to redraw for each
+                    if e.count == 0 {
+                        result.push(Event::Exposed);
+                    }
+                }
+                x11::xlib::VisibilityNotify => {
+                    let e = x11::xlib::XVisibilityEvent::from(cur_xevent);
+                    result.push(Event::VisibilityChanged {
+                        obscured: e.state == x11::xlib::VisibilityFullyObscured,
+                    });
+                }
+                x11::xlib::DestroyNotify => {
+                    let e = x11::xlib::XDestroyWindowEvent::from(cur_xevent);
+                    if e.window == self.root_window_id {
+                        result.push(Event::Destroyed);
+                    }
+                }
                 _ => {}
             }
         }
@@ -115,6 +212,20 @@ impl SizedWindow for ScreensaverWindow {
         (attrs.width as u32, attrs.height as u32)
     }
 }
+impl Drop for ScreensaverWindow {
+    fn drop(&mut self) {
+        unsafe {
+            // xscreensaver's window belongs to xscreensaver, and may well be gone already
+            if self.owns_window {
+                XUnmapWindow(self.dpy, self.root_window_id);
+                XDestroyWindow(self.dpy, self.root_window_id);
+            }
+            // Flushes anything still queued
+            XCloseDisplay(self.dpy);
+        }
+    }
+}
+
 /// Allows wgpu to create a surface from ScreensaverWindow
 unsafe impl raw_window_handle::HasRawWindowHandle for ScreensaverWindow {
     fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
@@ -0,0 +1,346 @@
+//! Renders scenes offscreen and compares them with the reference images in
+//! `tests/golden`. Run with `UPDATE_GOLDEN=1` to accept new renders as the
+//! reference after an intentional change.
+
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 200;
+/// Largest per channel difference still considered the same pixel, GPUs
+/// don't all rasterize and blend identically.
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Fraction of pixels allowed to differ by more than that, for edges.
+const PIXEL_TOLERANCE: f64 = 0.005;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Render frames `0..=last` of `scene` with `args` and return the directory
+/// they were written to.
+fn render(scene: &str, args: &[&str], last: u32) -> PathBuf {
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("golden-{}", scene));
+    let _ = std::fs::remove_dir_all(&output);
+    let geometry = format!("{}x{}", WIDTH, HEIGHT);
+    let frames = (last + 1).to_string();
+    let status = Command::new(env!("CARGO_BIN_EXE_xscreensaver_wgpu"))
+        .args(["-geometry", &geometry, "-render-frames", &frames, "-output"])
+        .arg(&output)
+        .args(args)
+        .status()
+        .expect("can run the hack");
+    assert!(status.success(), "rendering {} failed: {}", scene, status);
+    output
+}
+
+/// Pixels differing by more than the tolerance, and an image highlighting
+/// them in red over a dimmed copy of the expected image.
+fn diff(expected: &RgbaImage, actual: &RgbaImage) -> (usize, RgbaImage) {
+    let mut differing = 0;
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+    for (x, y, expected_pixel) in expected.enumerate_pixels() {
+        let actual_pixel = actual.get_pixel(x, y);
+        let same = expected_pixel
+            .0
+            .iter()
+            .zip(actual_pixel.0.iter())
+            .all(|(e, a)| e.abs_diff(*a) <= CHANNEL_TOLERANCE);
+        let pixel = if same {
+            let [r, g, b, _] = expected_pixel.0;
+            Rgba([r / 4, g / 4, b / 4, 255])
+        } else {
+            differing += 1;
+            Rgba([255, 0, 0, 255])
+        };
+        diff.put_pixel(x, y, pixel);
+    }
+    (differing, diff)
+}
+
+/// Compare the given frames of a render with the references named
+/// `<scene>_<frame>.png`.
+fn check_frames(scene: &str, args: &[&str], frames: &[u32]) {
+    let last = *frames.iter().max().expect("at least one frame");
+    let output = render(scene, args, last);
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for frame in frames {
+        let actual_path = output.join(format!("frame_{:05}.png", frame));
+        let golden_path = golden_dir().join(format!("{}_{:05}.png", scene, frame));
+        let actual = image::open(&actual_path)
+            .unwrap_or_else(|e| panic!("can't read {}: {}", actual_path.display(), e))
+            .to_rgba8();
+
+        if update {
+            std::fs::create_dir_all(golden_dir()).expect("can create the golden directory");
+            actual.save(&golden_path).expect("can write the golden image");
+            continue;
+        }
+
+        let expected = image::open(&golden_path)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "can't read {}, run with UPDATE_GOLDEN=1 to create it: {}",
+                    golden_path.display(),
+                    e
+                )
+            })
+            .to_rgba8();
+        if expected.dimensions() != actual.dimensions() {
+            failures.push(format!(
+                "{}: expected {:?}, rendered {:?}",
+                golden_path.display(),
+                expected.dimensions(),
+                actual.dimensions()
+            ));
+            continue;
+        }
+
+        let (differing, diff_image) = diff(&expected, &actual);
+        let total = (expected.width() * expected.height()) as f64;
+        if differing as f64 / total > PIXEL_TOLERANCE {
+            let diff_path = output.join(format!("diff_{:05}.png", frame));
+            diff_image.save(&diff_path).expect("can write the diff image");
+            failures.push(format!(
+                "{}: {} pixels differ, rendered {}, diff {}",
+                golden_path.display(),
+                differing,
+                actual_path.display(),
+                diff_path.display()
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn ground() {
+    check_frames("ground", &[], &[0, 10, 50, 99]);
+}
+
+#[test]
+fn ground_with_options() {
+    check_frames(
+        "ground_options",
+        &["-speed", "2", "-color", "#19e6ff"],
+        &[0, 13],
+    );
+}
+
+#[test]
+fn ground_without_msaa() {
+    check_frames("ground_no_msaa", &["-samples", "1"], &[0, 10]);
+}
+
+#[test]
+fn msaa_smooths_edges() {
+    // Otherwise the goldens could be updated with MSAA quietly off. Without
+    // bloom, what's left to tell them apart is the edges of the mountains.
+    let frame = |samples: &str| {
+        let output = render(
+            &format!("msaa_{}", samples),
+            &["-style", "terrain", "-post", "", "-samples", samples],
+            0,
+        );
+        image::open(output.join("frame_00000.png"))
+            .expect("can read the frame")
+            .to_rgba8()
+    };
+    let (differing, _) = diff(&frame("4"), &frame("1"));
+    let total = (WIDTH * HEIGHT) as f64;
+    assert!(
+        differing as f64 / total > PIXEL_TOLERANCE,
+        "4x MSAA renders the same as no MSAA"
+    );
+}
+
+#[test]
+fn ground_lines() {
+    check_frames("ground_lines", &["-style", "lines"], &[0, 10]);
+}
+
+#[test]
+fn ground_lines_with_options() {
+    check_frames(
+        "ground_lines_options",
+        &[
+            "-style", "lines", "-count", "40", "-speed", "2", "-color", "#19e6ff",
+        ],
+        &[0, 13],
+    );
+}
+
+#[test]
+fn ground_lines_with_variation() {
+    check_frames(
+        "ground_lines_variation",
+        &["-style", "lines", "-variation", "1"],
+        &[0, 30],
+    );
+}
+
+#[test]
+fn ground_lines_with_variation_across_a_period() {
+    // At this speed the lines move one spacing every 10 frames, where they
+    // used to all trade looks with their neighbours
+    check_frames(
+        "ground_lines_variation_period",
+        &["-style", "lines", "-variation", "1", "-speed", "1.25"],
+        &[9, 11],
+    );
+}
+
+#[test]
+fn ground_terrain() {
+    check_frames("ground_terrain", &["-style", "terrain"], &[0, 40]);
+}
+
+#[test]
+fn ground_terrain_filled() {
+    check_frames(
+        "ground_terrain_filled",
+        &["-style", "terrain", "-terrain-mesh", "filled", "-seed", "7"],
+        &[0, 40],
+    );
+}
+
+#[test]
+fn ground_terrain_along_a_camera_path() {
+    // The path starts well past the chunks the terrain begins with
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/paths/ahead.path");
+    check_frames(
+        "ground_terrain_path",
+        &["-style", "terrain", "-camera-path", path],
+        &[0, 10],
+    );
+}
+
+#[test]
+fn sky_with_options() {
+    check_frames(
+        "sky_options",
+        &["-stars", "-sun-size", "0.4", "-sky-horizon", "#0a3a5c"],
+        &[0, 20],
+    );
+}
+
+#[test]
+fn ground_without_post() {
+    check_frames("ground_no_post", &["-post", ""], &[0, 10]);
+}
+
+#[test]
+fn bloom_with_options() {
+    check_frames(
+        "bloom_options",
+        &[
+            "-bloom-threshold",
+            "0.2",
+            "-bloom-intensity",
+            "1.5",
+            "-bloom-levels",
+            "3",
+        ],
+        &[0, 10],
+    );
+}
+
+#[test]
+fn crt_effects() {
+    // Grain is left out, its noise is too GPU specific to compare
+    check_frames(
+        "crt",
+        &[
+            "-phosphor",
+            "-scanlines",
+            "-aberration",
+            "-aberration-amount",
+            "4",
+            "-barrel",
+            "-vignette",
+        ],
+        &[0, 10],
+    );
+}
+
+#[test]
+fn shadertoy() {
+    check_frames("shadertoy", &["-hack", "shadertoy"], &[0, 30]);
+}
+
+#[test]
+fn shadertoy_glsl() {
+    let shader = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/sunset.glsl");
+    check_frames(
+        "shadertoy_glsl",
+        &["-hack", "shadertoy", "-shader", shader],
+        &[0, 30],
+    );
+}
+
+#[test]
+fn shadertoy_wgsl() {
+    let shader = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/rings.wgsl");
+    check_frames(
+        "shadertoy_wgsl",
+        &["-hack", "shadertoy", "-shader", shader],
+        &[0, 30],
+    );
+}
+
+#[test]
+fn shadertoy_passes() {
+    let shader = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/trails/trails.passes");
+    check_frames(
+        "shadertoy_passes",
+        &["-hack", "shadertoy", "-shader", shader],
+        &[0, 30],
+    );
+}
+
+#[test]
+fn slideshow() {
+    let images = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/slideshow");
+    check_frames(
+        "slideshow",
+        &[
+            "-hack",
+            "slideshow",
+            "-image-directory",
+            images,
+            "-recursive",
+            "-duration",
+            "1",
+            "-transition-time",
+            "0.5",
+        ],
+        &[0, 52, 75, 100],
+    );
+}
+
+#[test]
+fn slideshow_transitions() {
+    let images = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/slideshow");
+    for transition in ["slide", "dissolve"] {
+        check_frames(
+            &format!("slideshow_{}", transition),
+            &[
+                "-hack",
+                "slideshow",
+                "-image-directory",
+                images,
+                "-duration",
+                "1",
+                "-transition-time",
+                "0.5",
+                "-transition",
+                transition,
+            ],
+            &[52],
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::process::{Child, Command, ExitStatus};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Give the hack some time to get going, send it `signal` and wait for it
+/// to exit on its own.
+fn exit_status_after(signal: libc::c_int) -> ExitStatus {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_xscreensaver_wgpu"))
+        .args(["-offscreen", "-geometry", "64x64"])
+        .spawn()
+        .expect("can spawn the hack");
+    sleep(Duration::from_secs(3));
+    if let Ok(Some(status)) = child.try_wait() {
+        panic!("hack exited before being signalled: {}", status);
+    }
+    unsafe { libc::kill(child.id() as libc::pid_t, signal) };
+    wait_with_timeout(&mut child, Duration::from_secs(10))
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> ExitStatus {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().expect("can wait for the hack") {
+            return status;
+        }
+        if start.elapsed() > timeout {
+            child.kill().ok();
+            panic!("hack didn't exit within {:?}", timeout);
+        }
+        sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn exits_cleanly_on_sigterm() {
+    assert_eq!(exit_status_after(libc::SIGTERM).code(), Some(0));
+}
+
+#[test]
+fn exits_cleanly_on_sigint() {
+    assert_eq!(exit_status_after(libc::SIGINT).code(), Some(0));
+}
+
+#[test]
+fn exits_cleanly_on_sighup() {
+    assert_eq!(exit_status_after(libc::SIGHUP).code(), Some(0));
+}